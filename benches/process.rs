@@ -0,0 +1,157 @@
+// Benchmarks process sampling against a synthetic procfs tree of 10k processes, each with a
+// thread, a few fds and a listening TCP socket. The tree is written to a temporary directory
+// when the benchmarks start.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use rtop::core::cpu::{read_kernel_stat, KernelStat};
+use rtop::core::process::{read_process_data, ProcHistory};
+use rtop::core::source::RootedSource;
+use rtop::core::thread::ThreadSelection;
+use rtop::core::users::UserResolver;
+
+const PROCESSES: i32 = 10_000;
+const FIRST_PID: i32 = 1000;
+const TOTAL_MEMORY_BYTES: u64 = 64 << 30;
+
+fn write(path: &Path, contents: &str) {
+    fs::write(path, contents).unwrap();
+}
+
+fn stat_line(pid: i32, comm: &str, utime: u64) -> String {
+    format!(
+        "{pid} ({comm}) S 1 {pid} {pid} 0 -1 4194304 100 0 0 0 {utime} 50 0 0 20 0 1 0 {start} \
+         104857600 2560 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0 0 0 0 0 0 0 \
+         0 0\n",
+        start = 1000 + pid,
+    )
+}
+
+fn status(pid: i32, comm: &str) -> String {
+    format!(
+        "Name:\t{comm}\nUmask:\t0022\nState:\tS (sleeping)\nTgid:\t{pid}\nNgid:\t0\nPid:\t{pid}\n\
+         PPid:\t1\nTracerPid:\t0\nUid:\t1000\t1000\t1000\t1000\nGid:\t1000\t1000\t1000\t1000\n\
+         FDSize:\t64\nGroups:\t1000\nNStgid:\t{pid}\nNSpid:\t{pid}\nNSpgid:\t{pid}\nNSsid:\t{pid}\n\
+         Threads:\t1\nSigQ:\t0/63494\nSigPnd:\t0000000000000000\nShdPnd:\t0000000000000000\n\
+         SigBlk:\t0000000000000000\nSigIgn:\t0000000000000000\nSigCgt:\t0000000000000000\n\
+         CapInh:\t0000000000000000\nCapPrm:\t0000000000000000\nCapEff:\t0000000000000000\n\
+         CapBnd:\t000001ffffffffff\nCapAmb:\t0000000000000000\nNoNewPrivs:\t0\nSeccomp:\t0\n\
+         Cpus_allowed:\tff\nCpus_allowed_list:\t0-7\nMems_allowed:\t1\nMems_allowed_list:\t0\n\
+         voluntary_ctxt_switches:\t10\nnonvoluntary_ctxt_switches:\t2\n"
+    )
+}
+
+// Writes the system-wide files and every process under `root`, and returns it.
+fn synthetic_proc(root: &Path) -> PathBuf {
+    let proc_root = root.join("proc");
+    fs::create_dir_all(proc_root.join("net")).unwrap();
+    write(
+        &proc_root.join("stat"),
+        "cpu  100000 0 30000 800000 0 0 0 0 0 0\ncpu0 100000 0 30000 800000 0 0 0 0 0 0\n\
+         btime 1700000000\n",
+    );
+    write(
+        &proc_root.join("meminfo"),
+        &format!("MemTotal: {} kB\n", TOTAL_MEMORY_BYTES / 1024),
+    );
+
+    let mut tcp = String::from(
+        "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  \
+         timeout inode\n",
+    );
+    for n in 0..PROCESSES {
+        let pid = FIRST_PID + n;
+        let comm = format!("worker-{}", n);
+        let dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(dir.join("task").join(pid.to_string())).unwrap();
+        fs::create_dir(dir.join("fd")).unwrap();
+
+        write(&dir.join("stat"), &stat_line(pid, &comm, n as u64));
+        write(
+            &dir.join("task").join(pid.to_string()).join("stat"),
+            &stat_line(pid, &comm, n as u64),
+        );
+        write(&dir.join("status"), &status(pid, &comm));
+        write(
+            &dir.join("cmdline"),
+            &format!("/usr/bin/{}\0--id\0{}\0", comm, n),
+        );
+        write(&dir.join("statm"), "25600 2560 1024 10 0 5000 0\n");
+        write(
+            &dir.join("io"),
+            "rchar: 1000\nwchar: 2000\nsyscr: 10\nsyscw: 20\nread_bytes: 4096\n\
+             write_bytes: 8192\ncancelled_write_bytes: 0\n",
+        );
+        write(&dir.join("cgroup"), "0::/system.slice/worker.service\n");
+
+        let inode = 100_000 + n as u64;
+        symlink("/dev/null", dir.join("fd/0")).unwrap();
+        symlink("pipe:[1]", dir.join("fd/1")).unwrap();
+        symlink(format!("socket:[{}]", inode), dir.join("fd/3")).unwrap();
+        writeln!(
+            tcp,
+            "{:4}: 00000000:{:04X} 00000000:0000 0A 00000000:00000000 00:00000000 00000000  \
+             1000        0 {} 1 0000000000000000 100 0 0 10 0",
+            n,
+            10_000 + n,
+            inode
+        )
+        .unwrap();
+    }
+    write(&proc_root.join("net/tcp"), &tcp);
+
+    proc_root
+}
+
+fn bench_read_process_data(c: &mut Criterion) {
+    let root = std::env::temp_dir().join(format!("rtop-bench-{}", std::process::id()));
+    let proc_root = synthetic_proc(&root);
+    let source = RootedSource::new(&proc_root, root.join("sys"));
+    let kernel_stat = read_kernel_stat(&source).unwrap();
+    let prev_kernel_stat = KernelStat::default();
+    let users = UserResolver::new();
+
+    let sample = |history: &mut ProcHistory| {
+        read_process_data(
+            &source,
+            (&kernel_stat, &prev_kernel_stat),
+            history,
+            Some(1.0),
+            (false, &ThreadSelection::All),
+            TOTAL_MEMORY_BYTES,
+            &users,
+        )
+        .unwrap()
+    };
+
+    let mut group = c.benchmark_group("read_process_data");
+    group.sample_size(10);
+
+    // First tick: every cmdline, status and fd directory is read
+    group.bench_function("10k processes, first tick", |b| {
+        b.iter_batched(
+            ProcHistory::default,
+            |mut history| sample(&mut history),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // Later ticks: cached details are reused, and fds are not scanned again as long as no
+    // process opens or closes any, nor opens a socket
+    let mut history = ProcHistory::default();
+    sample(&mut history);
+    group.bench_function("10k processes, later tick", |b| {
+        b.iter(|| sample(&mut history))
+    });
+
+    group.finish();
+    fs::remove_dir_all(&root).unwrap();
+}
+
+criterion_group!(benches, bench_read_process_data);
+criterion_main!(benches);
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use crate::core::error::RTopError;
+use crate::core::process::SortColumn;
+use crate::settings::{Overrides, Settings, MAX_INTERVAL, MIN_INTERVAL};
+use crate::ui::app::columns::ProcColumn;
+use crate::ui::keys::{parse_key, Action};
+use crate::ui::theme::parse_color;
+
+// Contents of the config file. Everything is optional; what is left out keeps its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    // Seconds between two refreshes
+    pub interval: Option<f64>,
+    // Same as --current-usage
+    pub current_usage: Option<bool>,
+    // Column processes are sorted by, and whether largest first
+    pub sort: Option<String>,
+    pub sort_descending: Option<bool>,
+    // Columns of the process table, in order
+    pub columns: Option<Vec<String>>,
+    pub theme: ThemeConfig,
+    // Keys of each action
+    pub keys: BTreeMap<String, KeyList>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    pub text: Option<String>,
+    pub accent: Option<String>,
+    pub header: Option<String>,
+    pub selection_fg: Option<String>,
+    pub selection_bg: Option<String>,
+    pub warning: Option<String>,
+    pub critical: Option<String>,
+}
+
+// One key or several for an action.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn keys(&self) -> &[String] {
+        match self {
+            KeyList::One(key) => std::slice::from_ref(key),
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+fn config_error(msg: String) -> RTopError {
+    RTopError { err_msg: msg }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, RTopError> {
+        toml::from_str(text).map_err(|err| config_error(err.to_string()))
+    }
+
+    // Overwrites the settings the config file sets. Names and values are checked here, so that
+    // a typo is reported instead of silently ignored.
+    pub fn apply(&self, settings: &mut Settings) -> Result<(), RTopError> {
+        if let Some(secs) = self.interval {
+            settings.interval = match Duration::try_from_secs_f64(secs) {
+                Ok(interval) if (MIN_INTERVAL..=MAX_INTERVAL).contains(&interval) => interval,
+                _ => {
+                    return Err(config_error(format!(
+                        "interval must be between {} and {} seconds",
+                        MIN_INTERVAL.as_secs_f64(),
+                        MAX_INTERVAL.as_secs_f64()
+                    )))
+                }
+            };
+        }
+        if let Some(current_usage) = self.current_usage {
+            settings.use_current_cpu_total = current_usage;
+        }
+        if let Some(sort) = &self.sort {
+            settings.sort_column = SortColumn::from_name(sort)
+                .ok_or_else(|| config_error(format!("unknown sort column '{}'", sort)))?;
+        }
+        settings.sort_descending = self.sort_descending;
+
+        if let Some(names) = &self.columns {
+            let mut columns = Vec::new();
+            for name in names {
+                let column = ProcColumn::from_name(name)
+                    .ok_or_else(|| config_error(format!("unknown column '{}'", name)))?;
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+            if columns.is_empty() {
+                return Err(config_error("columns must not be empty".to_string()));
+            }
+            settings.columns = columns;
+        }
+
+        let theme = &mut settings.theme;
+        let colors = [
+            ("text", &self.theme.text, &mut theme.text),
+            ("accent", &self.theme.accent, &mut theme.accent),
+            ("header", &self.theme.header, &mut theme.header),
+            (
+                "selection-fg",
+                &self.theme.selection_fg,
+                &mut theme.selection_fg,
+            ),
+            (
+                "selection-bg",
+                &self.theme.selection_bg,
+                &mut theme.selection_bg,
+            ),
+            ("warning", &self.theme.warning, &mut theme.warning),
+            ("critical", &self.theme.critical, &mut theme.critical),
+        ];
+        for (name, text, color) in colors {
+            if let Some(text) = text {
+                *color = parse_color(text).ok_or_else(|| {
+                    config_error(format!("unknown colour '{}' for theme.{}", text, name))
+                })?;
+            }
+        }
+
+        for (name, keys) in &self.keys {
+            let action = Action::from_name(name)
+                .ok_or_else(|| config_error(format!("unknown action '{}' in keys", name)))?;
+            let keys = keys
+                .keys()
+                .iter()
+                .map(|key| {
+                    parse_key(key).ok_or_else(|| {
+                        config_error(format!("unknown key '{}' for keys.{}", key, name))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            settings.keys.bind(action, &keys);
+        }
+
+        Ok(())
+    }
+}
+
+// $XDG_CONFIG_HOME/rtop/config.toml, where XDG_CONFIG_HOME defaults to ~/.config.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("rtop").join("config.toml"))
+}
+
+// Builds the settings from the defaults, the config file and the command line, in that order
+// of precedence, as often as the config file changes.
+#[derive(Debug, Clone)]
+pub struct SettingsSource {
+    pub config_path: Option<PathBuf>,
+    // Whether a missing config file is an error, as when it was given on the command line
+    pub config_required: bool,
+    pub overrides: Overrides,
+}
+
+impl SettingsSource {
+    pub fn load(&self) -> Result<Settings, RTopError> {
+        let mut settings = Settings::default();
+        if let Some(path) = &self.config_path {
+            match fs::read_to_string(path) {
+                Ok(text) => Config::parse(&text)
+                    .and_then(|config| config.apply(&mut settings))
+                    .map_err(|err| config_error(format!("{}: {}", path.display(), err.err_msg)))?,
+                Err(err) if err.kind() == ErrorKind::NotFound && !self.config_required => {}
+                Err(err) => {
+                    return Err(config_error(format!("{}: {}", path.display(), err)));
+                }
+            }
+        }
+        self.overrides.apply(&mut settings);
+        Ok(settings)
+    }
+
+    // When the config file was last changed, if it exists. A change means it should be loaded
+    // again.
+    pub fn config_modified(&self) -> Option<SystemTime> {
+        let path = self.config_path.as_ref()?;
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_config() {
+        let config = Config::parse(
+            "interval = 0.5\nsort = \"mem\"\ncolumns = [\"pid\", \"cpu\", \"pid\"]\n\
+             [theme]\nwarning = \"#ff8700\"\n[keys]\nquit = [\"x\", \"esc\"]\n",
+        )
+        .unwrap();
+        let mut settings = Settings::default();
+        config.apply(&mut settings).unwrap();
+
+        assert_eq!(settings.interval, Duration::from_millis(500));
+        assert_eq!(settings.sort_column, SortColumn::from_name("mem").unwrap());
+        assert_eq!(settings.columns, vec![ProcColumn::Pid, ProcColumn::Cpu]);
+        assert_eq!(
+            settings.theme.warning,
+            tui::style::Color::Rgb(0xff, 0x87, 0x00)
+        );
+        assert_eq!(
+            settings.keys.action(crossterm::event::KeyCode::Esc),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        let error = |text: &str| {
+            Config::parse(text)
+                .and_then(|config| config.apply(&mut Settings::default()))
+                .unwrap_err()
+                .err_msg
+        };
+
+        assert_eq!(error("sort = \"nope\""), "unknown sort column 'nope'");
+        assert_eq!(error("columns = []"), "columns must not be empty");
+        assert_eq!(
+            error("interval = 100"),
+            "interval must be between 0.1 and 60 seconds"
+        );
+        assert_eq!(
+            error("[keys]\njump = \"j\""),
+            "unknown action 'jump' in keys"
+        );
+        assert!(error("colour = 1").contains("unknown field"));
+    }
+
+    #[test]
+    fn command_line_overrides_current_usage_both_ways() {
+        let mut settings = Settings::default();
+        Config::parse("current-usage = true")
+            .and_then(|config| config.apply(&mut settings))
+            .unwrap();
+
+        let mut overrides = Overrides::default();
+        overrides.apply(&mut settings);
+        assert!(settings.use_current_cpu_total);
+
+        overrides.use_current_cpu_total = Some(false);
+        overrides.apply(&mut settings);
+        assert!(!settings.use_current_cpu_total);
+    }
+}
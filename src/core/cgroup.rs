@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use procfs::process::Process;
+use procfs::ProcessCgroup;
+
+use crate::core::pressure::{read_cgroup_pressure, PressureData};
+use crate::core::process::{Pid, ProcData};
+use crate::core::source::DataSource;
+
+// Where cgroup v2 is usually mounted under sysfs, in unified and hybrid hierarchies
+// respectively.
+const CGROUP2_DEFAULT_MOUNTS: [&str; 2] = ["fs/cgroup", "fs/cgroup/unified"];
+
+// Usage of a cgroup v2 control group and of the processes in it.
+#[derive(Debug, Clone, Default)]
+pub struct CgroupData {
+    // Path of the cgroup relative to the cgroup2 mount, e.g. /system.slice/sshd.service.
+    pub path: String,
+
+    // Number of ancestors of the cgroup below the root.
+    pub depth: usize,
+
+    // Processes directly in this cgroup.
+    pub pids: Vec<Pid>,
+
+    // Processes in this cgroup or any of its descendants.
+    pub total_process_count: usize,
+
+    // CPU usage of the whole cgroup as a percentage of every CPU, from cpu.stat's usage_usec.
+    pub cpu_usage_percent: f64,
+
+    // Total CPU time consumed by the cgroup, in microseconds.
+    pub cpu_usage_usec: Option<u64>,
+
+    // Memory charged to the cgroup (memory.current), in bytes.
+    pub memory_current_bytes: Option<u64>,
+
+    // Memory limit of the cgroup (memory.max), in bytes. None if unlimited or unknown.
+    pub memory_max_bytes: Option<u64>,
+
+    // Bytes read and written by the cgroup on every device (io.stat's rbytes and wbytes).
+    pub io_read_bytes: Option<u64>,
+    pub io_write_bytes: Option<u64>,
+
+    // Per-second rates of io_read_bytes and io_write_bytes since the last sample.
+    pub io_read_bytes_per_sec: f64,
+    pub io_write_bytes_per_sec: f64,
+
+    // Time the cgroup's tasks were stalled on CPU, memory and I/O (cpu.pressure etc.).
+    pub pressure: PressureData,
+}
+
+impl CgroupData {
+    // Last component of the path: the slice, scope or service name.
+    pub fn name(&self) -> &str {
+        match self.path.rsplit_once('/') {
+            Some((_, name)) if !name.is_empty() => name,
+            _ => "/",
+        }
+    }
+}
+
+// Counters kept between ticks to compute cgroup rates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CgroupCounters {
+    cpu_usage_usec: Option<u64>,
+    io_read_bytes: Option<u64>,
+    io_write_bytes: Option<u64>,
+}
+
+// Path of the cgroup v2 hierarchy among the cgroups of a process.
+pub fn get_unified_cgroup(cgroups: &[ProcessCgroup]) -> Option<String> {
+    cgroups
+        .iter()
+        .find(|cgroup| cgroup.hierarchy == 0)
+        .map(|cgroup| cgroup.pathname.clone())
+}
+
+// Finds where the cgroup2 filesystem is mounted.
+pub fn find_cgroup2_mount(source: &dyn DataSource) -> Option<PathBuf> {
+    if let Ok(mounts) = Process::new_with_root(source.proc_path("self")).and_then(|p| p.mountinfo())
+    {
+        if let Some(mount) = mounts.into_iter().find(|m| m.fs_type == "cgroup2") {
+            return Some(source.rebase_sys_path(&mount.mount_point));
+        }
+    }
+
+    CGROUP2_DEFAULT_MOUNTS
+        .iter()
+        .map(|path| source.sys_path(path))
+        .find(|path| path.join("cgroup.controllers").exists())
+}
+
+// Reads every cgroup holding at least one of the given processes, together with all of its
+// ancestors so slices show the usage of everything below them. Cgroups are returned in path
+// order, so every cgroup comes right before its descendants.
+pub fn read_cgroup_data(
+    mount: &Path,
+    processes: &[ProcData],
+    counters: &mut HashMap<String, CgroupCounters>,
+    elapsed_secs: Option<f64>,
+    num_cpus: usize,
+) -> Vec<CgroupData> {
+    let mut groups: BTreeMap<String, CgroupData> = BTreeMap::new();
+
+    for process in processes {
+        let path = match &process.cgroup {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let mut ancestor = path.as_str();
+        loop {
+            let group = groups
+                .entry(ancestor.to_string())
+                .or_insert_with(|| CgroupData {
+                    path: ancestor.to_string(),
+                    ..Default::default()
+                });
+            group.total_process_count += 1;
+            if ancestor == path {
+                group.pids.push(process.pid);
+            }
+
+            ancestor = match ancestor.rsplit_once('/') {
+                Some(("", _)) if ancestor != "/" => "/",
+                Some((parent, _)) if !parent.is_empty() => parent,
+                _ => break,
+            };
+        }
+    }
+
+    let mut new_counters = HashMap::with_capacity(groups.len());
+    let data = groups
+        .into_values()
+        .map(|mut group| {
+            let dir = mount.join(group.path.trim_start_matches('/'));
+            group.depth = group.path.matches('/').count() - usize::from(group.path == "/");
+
+            group.cpu_usage_usec = read_keyed_value(&dir.join("cpu.stat"), "usage_usec");
+            group.memory_current_bytes = read_single_value(&dir.join("memory.current"));
+            group.memory_max_bytes = read_single_value(&dir.join("memory.max"));
+            group.pressure = read_cgroup_pressure(&dir);
+            if let Some((read, write)) = read_io_stat(&dir.join("io.stat")) {
+                group.io_read_bytes = Some(read);
+                group.io_write_bytes = Some(write);
+            }
+
+            let new = CgroupCounters {
+                cpu_usage_usec: group.cpu_usage_usec,
+                io_read_bytes: group.io_read_bytes,
+                io_write_bytes: group.io_write_bytes,
+            };
+            if let (Some(prev), Some(secs)) = (counters.get(&group.path), elapsed_secs) {
+                if secs > 0.0 {
+                    let delta = |new: Option<u64>, prev: Option<u64>| match (new, prev) {
+                        (Some(new), Some(prev)) => new.saturating_sub(prev) as f64,
+                        _ => 0.0,
+                    };
+                    let cpu_secs = delta(new.cpu_usage_usec, prev.cpu_usage_usec) / 1_000_000.0;
+                    group.cpu_usage_percent = cpu_secs / (secs * num_cpus.max(1) as f64) * 100.0;
+                    group.io_read_bytes_per_sec =
+                        delta(new.io_read_bytes, prev.io_read_bytes) / secs;
+                    group.io_write_bytes_per_sec =
+                        delta(new.io_write_bytes, prev.io_write_bytes) / secs;
+                }
+            }
+            new_counters.insert(group.path.clone(), new);
+
+            group
+        })
+        .collect();
+
+    *counters = new_counters;
+    data
+}
+
+// Parses files made of `key value` lines, such as cpu.stat.
+pub fn parse_keyed_value(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.split_once(' ')?;
+        if k == key {
+            v.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+// Sums rbytes and wbytes over every device of an io.stat file.
+pub fn parse_io_stat(contents: &str) -> (u64, u64) {
+    let mut read = 0;
+    let mut write = 0;
+
+    for field in contents.split_whitespace() {
+        if let Some((key, value)) = field.split_once('=') {
+            match key {
+                "rbytes" => read += value.parse::<u64>().unwrap_or(0),
+                "wbytes" => write += value.parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    (read, write)
+}
+
+fn read_keyed_value(path: &Path, key: &str) -> Option<u64> {
+    parse_keyed_value(&fs::read_to_string(path).ok()?, key)
+}
+
+// Reads files holding a single number, such as memory.current. "max" reads as None.
+fn read_single_value(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn read_io_stat(path: &Path) -> Option<(u64, u64)> {
+    Some(parse_io_stat(&fs::read_to_string(path).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keyed_value() {
+        let cpu_stat = "usage_usec 123456\nuser_usec 100000\nsystem_usec 23456\n";
+
+        assert_eq!(parse_keyed_value(cpu_stat, "usage_usec"), Some(123456));
+        assert_eq!(parse_keyed_value(cpu_stat, "system_usec"), Some(23456));
+        assert_eq!(parse_keyed_value(cpu_stat, "nr_periods"), None);
+    }
+
+    #[test]
+    fn sums_io_stat_over_devices() {
+        let io_stat = "8:0 rbytes=1000 wbytes=200 rios=10 wios=2 dbytes=0 dios=0\n\
+                       259:0 rbytes=24 wbytes=6 rios=1 wios=1 dbytes=0 dios=0\n";
+
+        assert_eq!(parse_io_stat(io_stat), (1024, 206));
+        assert_eq!(parse_io_stat(""), (0, 0));
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::error::RTopError;
+use crate::core::system_reader::{SystemData, SystemReader};
+use crate::core::thread::ThreadSelection;
+
+enum Command {
+    SetInterval(Duration),
+    SetUseCurrentCpuTotal(bool),
+    SelectThreads(ThreadSelection),
+}
+
+// Samples the system on a thread of its own every interval, so that walking /proc never holds
+// up the thread drawing the UI. Each sample is handed over as an immutable snapshot. The
+// thread stops when the Collector is dropped or a snapshot can no longer be handed over.
+pub struct Collector {
+    commands: Sender<Command>,
+}
+
+impl Collector {
+    // Starts sampling right away. `publish` receives every sample, or the error reading it,
+    // and returns false once nobody wants them anymore.
+    pub fn spawn<F>(mut reader: SystemReader, interval: Duration, publish: F) -> Self
+    where
+        F: Fn(Result<Arc<SystemData>, RTopError>) -> bool + Send + 'static,
+    {
+        let (commands, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut interval = interval;
+            loop {
+                let started = Instant::now();
+                if !publish(reader.read_process_data().map(Arc::new)) {
+                    return;
+                }
+
+                // Wait for the next sample, taking in the commands sent meanwhile
+                loop {
+                    let timeout = (started + interval).saturating_duration_since(Instant::now());
+                    match rx.recv_timeout(timeout) {
+                        Ok(Command::SetInterval(new_interval)) => interval = new_interval,
+                        Ok(Command::SetUseCurrentCpuTotal(use_current_cpu_total)) => {
+                            reader.set_use_current_cpu_total(use_current_cpu_total)
+                        }
+                        // Sample again right away, so the threads show up without waiting
+                        Ok(Command::SelectThreads(threads)) => {
+                            reader.select_threads(threads);
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            }
+        });
+
+        Collector { commands }
+    }
+
+    // Also applies to the sample being waited for, so going from a long interval to a short
+    // one takes effect at once.
+    pub fn set_interval(&self, interval: Duration) {
+        let _ = self.commands.send(Command::SetInterval(interval));
+    }
+
+    pub fn set_use_current_cpu_total(&self, use_current_cpu_total: bool) {
+        let _ = self
+            .commands
+            .send(Command::SetUseCurrentCpuTotal(use_current_cpu_total));
+    }
+
+    pub fn select_threads(&self, threads: ThreadSelection) {
+        let _ = self.commands.send(Command::SelectThreads(threads));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::mpsc::Receiver;
+
+    use super::*;
+    use crate::core::source::RootedSource;
+
+    fn spawn(interval: Duration) -> (Collector, Receiver<Result<Arc<SystemData>, RTopError>>) {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let source = RootedSource::new(fixtures.join("proc"), fixtures.join("sys"));
+        let reader = SystemReader::new(Box::new(source), false);
+        let (tx, rx) = mpsc::channel();
+        let collector = Collector::spawn(reader, interval, move |sample| tx.send(sample).is_ok());
+        (collector, rx)
+    }
+
+    #[test]
+    fn shorter_interval_applies_to_the_sample_waited_for() {
+        let (collector, rx) = spawn(Duration::from_secs(60));
+        assert!(rx.recv_timeout(Duration::from_secs(5)).unwrap().is_ok());
+
+        collector.set_interval(Duration::from_millis(10));
+        assert!(rx.recv_timeout(Duration::from_secs(5)).unwrap().is_ok());
+        assert!(rx.recv_timeout(Duration::from_secs(5)).unwrap().is_ok());
+    }
+
+    #[test]
+    fn stops_when_dropped() {
+        let (collector, rx) = spawn(Duration::from_secs(60));
+        assert!(rx.recv_timeout(Duration::from_secs(5)).unwrap().is_ok());
+
+        // The thread drops its end of the channel as it returns
+        drop(collector);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).err(),
+            Some(RecvTimeoutError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use crate::core::namespace::{NamespaceKind, ProcNamespaces};
+
+// Length of the hex IDs docker, podman and containerd give to containers.
+const CONTAINER_ID_LEN: usize = 64;
+
+// Length IDs are shortened to for display, as `docker ps` does.
+const SHORT_ID_LEN: usize = 12;
+
+// Container runtimes recognised from cgroup paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+    Containerd,
+    CriO,
+    Lxc,
+}
+
+impl ContainerRuntime {
+    pub fn name(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+            ContainerRuntime::Containerd => "containerd",
+            ContainerRuntime::CriO => "crio",
+            ContainerRuntime::Lxc => "lxc",
+        }
+    }
+}
+
+// Container a process runs in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContainerInfo {
+    // Runtime that started the container, if its cgroup path gives it away. None for processes
+    // that only have namespaces of their own, and for Kubernetes containers whose cgroup path
+    // is the same whichever runtime runs them.
+    pub runtime: Option<ContainerRuntime>,
+
+    // Full ID of the container, or its name for LXC. None for processes that only have
+    // namespaces of their own.
+    pub id: Option<String>,
+}
+
+impl ContainerInfo {
+    // Short form shown in the process table, e.g. docker:4f2a9c1d8e7b, or just the short ID
+    // when the runtime is unknown.
+    pub fn label(&self) -> String {
+        let id = self.id.as_deref().map(|id| {
+            if id.len() == CONTAINER_ID_LEN {
+                &id[..SHORT_ID_LEN]
+            } else {
+                id
+            }
+        });
+        match (self.runtime, id) {
+            (Some(runtime), Some(id)) => format!("{}:{}", runtime.name(), id),
+            (Some(runtime), None) => runtime.name().to_string(),
+            (None, Some(id)) => id.to_string(),
+            (None, None) => "?".to_string(),
+        }
+    }
+}
+
+// Works out the container of a process from the paths of its cgroups (v1 and v2) and its
+// namespaces. A process with a pid and a mount namespace other than PID 1's lives in a
+// container, even when no runtime can be recognised from its cgroup. Host services with only a
+// private mount or network namespace (systemd's PrivateTmp or PrivateNetwork) and browser
+// sandboxes, which keep the host's mount namespace, are not containers. Returns None for
+// processes running on the host.
+pub(crate) fn detect_container<'a>(
+    cgroup_paths: impl IntoIterator<Item = &'a str>,
+    namespaces: &ProcNamespaces,
+    host_namespaces: &ProcNamespaces,
+) -> Option<ContainerInfo> {
+    if let Some((runtime, id)) = cgroup_paths.into_iter().find_map(parse_container_cgroup) {
+        return Some(ContainerInfo {
+            runtime,
+            id: Some(id),
+        });
+    }
+
+    if [NamespaceKind::Pid, NamespaceKind::Mnt]
+        .iter()
+        .all(|kind| namespaces.differs_from(host_namespaces, *kind))
+    {
+        Some(ContainerInfo {
+            runtime: None,
+            id: None,
+        })
+    } else {
+        None
+    }
+}
+
+// Recognises the cgroup layouts of the usual runtimes, with both the systemd and cgroupfs
+// drivers:
+//   /system.slice/docker-<id>.scope, /docker/<id>
+//   /machine.slice/libpod-<id>.scope/container, /libpod_parent/libpod-<id>
+//   /system.slice/cri-containerd-<id>.scope
+//   /kubepods.slice/.../crio-<id>.scope
+//   /lxc.payload.<name>, /lxc/<name>
+// and /kubepods/burstable/pod<uid>/<id>, which containerd, CRI-O and dockershim all use, so
+// its runtime is left unknown.
+pub fn parse_container_cgroup(path: &str) -> Option<(Option<ContainerRuntime>, String)> {
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+    for (idx, component) in components.iter().enumerate().rev() {
+        let unit = component.strip_suffix(".scope").unwrap_or(component);
+        let prefixed = [
+            ("docker-", ContainerRuntime::Docker),
+            ("libpod-", ContainerRuntime::Podman),
+            ("cri-containerd-", ContainerRuntime::Containerd),
+            ("crio-", ContainerRuntime::CriO),
+        ];
+        for (prefix, runtime) in prefixed {
+            if let Some(id) = unit.strip_prefix(prefix) {
+                if is_container_id(id) {
+                    return Some((Some(runtime), id.to_string()));
+                }
+            }
+        }
+
+        if let Some(name) = component.strip_prefix("lxc.payload.") {
+            return Some((Some(ContainerRuntime::Lxc), name.to_string()));
+        }
+
+        let parent = if idx > 0 { components[idx - 1] } else { "" };
+        match parent {
+            "lxc" | "lxc.payload" => {
+                return Some((Some(ContainerRuntime::Lxc), component.to_string()))
+            }
+            "docker" if is_container_id(component) => {
+                return Some((Some(ContainerRuntime::Docker), component.to_string()))
+            }
+            _ => {}
+        }
+        if is_container_id(component) && components[0].starts_with("kubepods") {
+            return Some((None, component.to_string()));
+        }
+    }
+
+    None
+}
+
+fn is_container_id(id: &str) -> bool {
+    id.len() == CONTAINER_ID_LEN && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4f1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8";
+
+    fn parse(path: &str) -> Option<(Option<ContainerRuntime>, String)> {
+        parse_container_cgroup(&path.replace("<id>", ID))
+    }
+
+    #[test]
+    fn recognises_runtimes() {
+        let docker = Some((Some(ContainerRuntime::Docker), ID.to_string()));
+        assert_eq!(parse("/system.slice/docker-<id>.scope"), docker);
+        assert_eq!(parse("/docker/<id>"), docker);
+
+        let podman = Some((Some(ContainerRuntime::Podman), ID.to_string()));
+        assert_eq!(parse("/machine.slice/libpod-<id>.scope/container"), podman);
+        assert_eq!(parse("/libpod_parent/libpod-<id>"), podman);
+
+        assert_eq!(
+            parse("/system.slice/cri-containerd-<id>.scope"),
+            Some((Some(ContainerRuntime::Containerd), ID.to_string()))
+        );
+        assert_eq!(
+            parse("/kubepods.slice/kubepods-besteffort.slice/crio-<id>.scope"),
+            Some((Some(ContainerRuntime::CriO), ID.to_string()))
+        );
+
+        let lxc = Some((Some(ContainerRuntime::Lxc), "web".to_string()));
+        assert_eq!(parse("/lxc.payload.web"), lxc);
+        assert_eq!(parse("/lxc/web"), lxc);
+    }
+
+    #[test]
+    fn kubepods_ids_have_no_runtime() {
+        assert_eq!(
+            parse("/kubepods/burstable/pod1234/<id>"),
+            Some((None, ID.to_string()))
+        );
+    }
+
+    #[test]
+    fn host_cgroups_are_not_containers() {
+        assert_eq!(parse("/user.slice/user-1000.slice/session-2.scope"), None);
+        assert_eq!(parse("/system.slice/docker.service"), None);
+        assert_eq!(parse("/docker/not-an-id"), None);
+        assert_eq!(parse("/"), None);
+    }
+
+    #[test]
+    fn shortens_ids_in_labels() {
+        let info = ContainerInfo {
+            runtime: Some(ContainerRuntime::Docker),
+            id: Some(ID.to_string()),
+        };
+
+        assert_eq!(info.label(), "docker:4f1a2b3c4d5e");
+
+        let info = ContainerInfo {
+            runtime: None,
+            id: Some(ID.to_string()),
+        };
+        assert_eq!(info.label(), "4f1a2b3c4d5e");
+    }
+
+    fn namespaces(inode: u64) -> ProcNamespaces {
+        ProcNamespaces {
+            pid: Some(inode),
+            net: Some(inode + 1),
+            mnt: Some(inode + 2),
+            uts: Some(inode + 3),
+            ipc: Some(inode + 4),
+            user: Some(inode + 5),
+            cgroup: Some(inode + 6),
+        }
+    }
+
+    #[test]
+    fn own_pid_and_mount_namespaces_mean_a_container() {
+        let host = namespaces(100);
+        let unknown = Some(ContainerInfo {
+            runtime: None,
+            id: None,
+        });
+        assert_eq!(detect_container([], &host, &host), None);
+        assert_eq!(detect_container([], &namespaces(200), &host), unknown);
+
+        let mut container = host;
+        container.pid = Some(1);
+        container.mnt = Some(2);
+        assert_eq!(detect_container([], &container, &host), unknown);
+    }
+
+    #[test]
+    fn other_private_namespaces_are_not_a_container() {
+        let host = namespaces(100);
+
+        // A PrivateTmp and PrivateNetwork service
+        let mut service = host;
+        service.mnt = Some(1);
+        service.net = Some(2);
+        assert_eq!(detect_container([], &service, &host), None);
+
+        // A browser sandbox
+        let mut sandbox = host;
+        sandbox.pid = Some(1);
+        sandbox.net = Some(2);
+        sandbox.user = Some(3);
+        assert_eq!(detect_container([], &sandbox, &host), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::io;
+
+use crate::core::source::DataSource;
+
+const STAT_PATH: &str = "stat";
+
+// Time a CPU spent in each state, in clock ticks, from a cpu line of /proc/stat. `user` and
+// `nice` include the time spent running guests (`guest` and `guest_nice`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+    pub guest: u64,
+    pub guest_nice: u64,
+}
+
+impl CpuTimes {
+    // Time spent idle, including while waiting for I/O.
+    pub fn idle_time(&self) -> u64 {
+        self.idle + self.iowait
+    }
+
+    // Time spent doing work. Guest time is already part of user and nice.
+    pub fn busy_time(&self) -> u64 {
+        self.user + self.nice + self.system + self.irq + self.softirq + self.steal
+    }
+}
+
+// Counters read from /proc/stat.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KernelStat {
+    // Times of all CPUs added up (the `cpu` line).
+    pub cpu: CpuTimes,
+
+    // Times of each online CPU with its number (the `cpuN` lines).
+    pub cores: Vec<(usize, CpuTimes)>,
+
+    // Context switches, interrupts and forks since boot.
+    pub context_switches: u64,
+    pub interrupts: u64,
+    pub forks: u64,
+
+    // Tasks currently runnable, and blocked waiting for I/O.
+    pub procs_running: u64,
+    pub procs_blocked: u64,
+
+    // Time the system booted, in seconds since the Unix epoch.
+    pub boot_time: u64,
+}
+
+pub fn read_kernel_stat(source: &dyn DataSource) -> io::Result<KernelStat> {
+    Ok(parse_kernel_stat(&source.read_proc(STAT_PATH)?))
+}
+
+pub fn parse_kernel_stat(contents: &str) -> KernelStat {
+    let mut stat = KernelStat::default();
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("cpu") => stat.cpu = parse_cpu_times(fields),
+            Some(name) if name.starts_with("cpu") => {
+                if let Ok(id) = name[3..].parse() {
+                    stat.cores.push((id, parse_cpu_times(fields)));
+                }
+            }
+            // Only the first value of the intr line, the total, is kept
+            Some(name) => {
+                let value = fields.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+                match name {
+                    "ctxt" => stat.context_switches = value,
+                    "intr" => stat.interrupts = value,
+                    "processes" => stat.forks = value,
+                    "procs_running" => stat.procs_running = value,
+                    "procs_blocked" => stat.procs_blocked = value,
+                    "btime" => stat.boot_time = value,
+                    _ => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    stat
+}
+
+// Parses the values of a cpu line. Older kernels have fewer columns; missing ones read as 0.
+fn parse_cpu_times<'a>(fields: impl Iterator<Item = &'a str>) -> CpuTimes {
+    let mut values = fields.map(|value| value.parse::<u64>().unwrap_or(0));
+    let mut next = || values.next().unwrap_or(0);
+
+    CpuTimes {
+        user: next(),
+        nice: next(),
+        system: next(),
+        idle: next(),
+        iowait: next(),
+        irq: next(),
+        softirq: next(),
+        steal: next(),
+        guest: next(),
+        guest_nice: next(),
+    }
+}
+
+// Share of the time between two samples a CPU spent in each state, in percent. Unlike in
+// CpuTimes, `user` and `nice` exclude guest time, so all the fields add up to 100.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuUsage {
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+    pub guest: f64,
+    pub guest_nice: f64,
+}
+
+impl CpuUsage {
+    pub fn between(new: &CpuTimes, prev: &CpuTimes) -> Self {
+        let delta = |new: u64, prev: u64| new.saturating_sub(prev) as f64;
+
+        let guest = delta(new.guest, prev.guest);
+        let guest_nice = delta(new.guest_nice, prev.guest_nice);
+        let user = (delta(new.user, prev.user) - guest).max(0.0);
+        let nice = (delta(new.nice, prev.nice) - guest_nice).max(0.0);
+        let system = delta(new.system, prev.system);
+        let idle = delta(new.idle, prev.idle);
+        let iowait = delta(new.iowait, prev.iowait);
+        let irq = delta(new.irq, prev.irq);
+        let softirq = delta(new.softirq, prev.softirq);
+        let steal = delta(new.steal, prev.steal);
+
+        let total =
+            user + nice + system + idle + iowait + irq + softirq + steal + guest + guest_nice;
+        if total <= 0.0 {
+            return CpuUsage::default();
+        }
+
+        let percent = |ticks: f64| ticks / total * 100.0;
+        CpuUsage {
+            user: percent(user),
+            nice: percent(nice),
+            system: percent(system),
+            idle: percent(idle),
+            iowait: percent(iowait),
+            irq: percent(irq),
+            softirq: percent(softirq),
+            steal: percent(steal),
+            guest: percent(guest),
+            guest_nice: percent(guest_nice),
+        }
+    }
+
+    // Percentage of the time the CPU was not idle or waiting for I/O.
+    pub fn busy_percent(&self) -> f64 {
+        self.user
+            + self.nice
+            + self.system
+            + self.irq
+            + self.softirq
+            + self.steal
+            + self.guest
+            + self.guest_nice
+    }
+}
+
+// Usage of every CPU since the previous sample.
+#[derive(Debug, Clone, Default)]
+pub struct CpuData {
+    pub total: CpuUsage,
+
+    // Usage of each online CPU with its number.
+    pub cores: Vec<(usize, CpuUsage)>,
+}
+
+impl CpuData {
+    pub fn between(new: &KernelStat, prev: &KernelStat) -> Self {
+        let cores = new
+            .cores
+            .iter()
+            .map(|(id, times)| {
+                let prev_times = prev
+                    .cores
+                    .iter()
+                    .find(|(prev_id, _)| prev_id == id)
+                    .map(|(_, times)| *times)
+                    .unwrap_or_default();
+                (*id, CpuUsage::between(times, &prev_times))
+            })
+            .collect();
+
+        CpuData {
+            total: CpuUsage::between(&new.cpu, &prev.cpu),
+            cores,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT: &str = "\
+cpu  4705 356 584 3699 23 0 23 0 10 2
+cpu0 1393 280 290 1866 13 0 18 0 5 1
+cpu2 3312 76 294 1833 10 0 5 0 5 1
+intr 114930548 113199788 3 0 5 263 0
+ctxt 1990473
+btime 1062191376
+processes 2915
+procs_running 1
+procs_blocked 0
+softirq 183433 0 21755 12 39 1137 231 21459 2263 0 116540
+";
+
+    #[test]
+    fn parses_kernel_stat() {
+        let stat = parse_kernel_stat(STAT);
+
+        assert_eq!(stat.cpu.user, 4705);
+        assert_eq!(stat.cpu.nice, 356);
+        assert_eq!(stat.cpu.guest, 10);
+        assert_eq!(stat.cpu.guest_nice, 2);
+        assert_eq!(stat.cores.len(), 2);
+        assert_eq!(stat.cores[1].0, 2);
+        assert_eq!(stat.cores[1].1.system, 294);
+        assert_eq!(stat.interrupts, 114930548);
+        assert_eq!(stat.context_switches, 1990473);
+        assert_eq!(stat.boot_time, 1062191376);
+        assert_eq!(stat.forks, 2915);
+        assert_eq!(stat.procs_running, 1);
+        assert_eq!(stat.procs_blocked, 0);
+    }
+
+    #[test]
+    fn missing_cpu_columns_read_as_zero() {
+        let stat = parse_kernel_stat("cpu 10 20 30 40\n");
+
+        assert_eq!(stat.cpu.idle, 40);
+        assert_eq!(stat.cpu.iowait, 0);
+        assert_eq!(stat.cpu.steal, 0);
+    }
+
+    #[test]
+    fn usage_excludes_guest_time_from_user() {
+        let prev = CpuTimes::default();
+        let new = CpuTimes {
+            user: 60,
+            idle: 40,
+            guest: 20,
+            ..Default::default()
+        };
+        let usage = CpuUsage::between(&new, &prev);
+
+        assert_eq!(usage.user, 40.0);
+        assert_eq!(usage.guest, 20.0);
+        assert_eq!(usage.idle, 40.0);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::core::source::DataSource;
+
+const DISKSTATS_PATH: &str = "diskstats";
+const CLASS_BLOCK_PATH: &str = "class/block";
+
+// /proc/diskstats counts sectors of 512 bytes, whatever the device's sector size.
+const SECTOR_SIZE: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskKind {
+    // A whole block device, such as a disk, a loop device or zram.
+    Disk,
+    Partition,
+    // A device-mapper volume (LVM, dm-crypt, ...).
+    DeviceMapper,
+}
+
+// Counters of a block device since boot, from a line of /proc/diskstats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskCounters {
+    pub reads: u64,
+    pub sectors_read: u64,
+    // Milliseconds spent on reads, added up over every request.
+    pub read_ms: u64,
+    pub writes: u64,
+    pub sectors_written: u64,
+    pub write_ms: u64,
+    // Requests currently in flight.
+    pub in_flight: u64,
+    // Milliseconds the device had at least one request in flight.
+    pub io_ms: u64,
+    // Milliseconds spent by all requests, weighted by the number in flight.
+    pub weighted_io_ms: u64,
+}
+
+// Activity of a block device since the last sample, like iostat -x shows it.
+#[derive(Debug, Clone, Default)]
+pub struct DiskData {
+    pub name: String,
+    pub kind: Option<DiskKind>,
+
+    // Name given by device-mapper (e.g. vg-root), for device-mapper volumes.
+    pub label: Option<String>,
+
+    pub totals: DiskCounters,
+
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub reads_per_sec: f64,
+    pub writes_per_sec: f64,
+
+    // Average time requests took to complete, queueing included, in milliseconds.
+    pub read_await_ms: f64,
+    pub write_await_ms: f64,
+
+    // Average number of requests waiting or being served.
+    pub queue_depth: f64,
+
+    // Percentage of the time the device was busy.
+    pub utilization_percent: f64,
+}
+
+// Reads every block device that has done any I/O since boot, in /proc/diskstats order, so that
+// partitions follow their disk. `counters` holds the counters of the previous sample and is
+// updated with the new ones.
+pub fn read_disk_data(
+    source: &dyn DataSource,
+    counters: &mut HashMap<String, DiskCounters>,
+    elapsed_secs: Option<f64>,
+) -> Vec<DiskData> {
+    let contents = match source.read_proc(DISKSTATS_PATH) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut new_counters = HashMap::new();
+    let disks = parse_diskstats(&contents)
+        .into_iter()
+        .filter(|(_, totals)| totals.reads + totals.writes > 0)
+        .map(|(name, totals)| {
+            let mut disk = match (counters.get(&name), elapsed_secs) {
+                (Some(prev), Some(secs)) => disk_activity(&totals, prev, secs),
+                _ => DiskData::default(),
+            };
+            let dir = source.sys_path(CLASS_BLOCK_PATH).join(&name);
+            disk.kind = disk_kind(&dir);
+            if disk.kind == Some(DiskKind::DeviceMapper) {
+                disk.label = fs::read_to_string(dir.join("dm/name"))
+                    .ok()
+                    .map(|label| label.trim().to_string());
+            }
+            disk.totals = totals;
+
+            new_counters.insert(name.clone(), totals);
+            disk.name = name;
+            disk
+        })
+        .collect();
+
+    *counters = new_counters;
+    disks
+}
+
+pub fn parse_diskstats(contents: &str) -> Vec<(String, DiskCounters)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(2);
+            let name = fields.next()?.to_string();
+            let values: Vec<u64> = fields.map(|v| v.parse().unwrap_or(0)).collect();
+            if values.len() < 11 {
+                return None;
+            }
+
+            Some((
+                name,
+                DiskCounters {
+                    reads: values[0],
+                    sectors_read: values[2],
+                    read_ms: values[3],
+                    writes: values[4],
+                    sectors_written: values[6],
+                    write_ms: values[7],
+                    in_flight: values[8],
+                    io_ms: values[9],
+                    weighted_io_ms: values[10],
+                },
+            ))
+        })
+        .collect()
+}
+
+// Rates and averages over the `secs` seconds between two samples of the same device.
+pub fn disk_activity(new: &DiskCounters, prev: &DiskCounters, secs: f64) -> DiskData {
+    if secs <= 0.0 {
+        return DiskData::default();
+    }
+
+    let delta = |new: u64, prev: u64| new.saturating_sub(prev) as f64;
+    let reads = delta(new.reads, prev.reads);
+    let writes = delta(new.writes, prev.writes);
+    let average = |ms: f64, requests: f64| if requests > 0.0 { ms / requests } else { 0.0 };
+
+    DiskData {
+        read_bytes_per_sec: delta(new.sectors_read, prev.sectors_read) * SECTOR_SIZE as f64 / secs,
+        write_bytes_per_sec: delta(new.sectors_written, prev.sectors_written) * SECTOR_SIZE as f64
+            / secs,
+        reads_per_sec: reads / secs,
+        writes_per_sec: writes / secs,
+        read_await_ms: average(delta(new.read_ms, prev.read_ms), reads),
+        write_await_ms: average(delta(new.write_ms, prev.write_ms), writes),
+        queue_depth: delta(new.weighted_io_ms, prev.weighted_io_ms) / (secs * 1000.0),
+        utilization_percent: (delta(new.io_ms, prev.io_ms) / (secs * 1000.0) * 100.0).min(100.0),
+        ..Default::default()
+    }
+}
+
+// Kind of block device, from its directory in /sys/class/block.
+fn disk_kind(dir: &Path) -> Option<DiskKind> {
+    if !dir.exists() {
+        None
+    } else if dir.join("dm").exists() {
+        Some(DiskKind::DeviceMapper)
+    } else if dir.join("partition").exists() {
+        Some(DiskKind::Partition)
+    } else {
+        Some(DiskKind::Disk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISKSTATS: &str = "\
+   8       0 sda 4710 1307 390262 3160 2561 2954 208344 5730 0 6110 9170 0 0 0 0
+   8       1 sda1 4600 1307 385000 3100 2500 2954 208000 5700 0 6000 8800
+ 253       0 dm-0 12
+";
+
+    #[test]
+    fn parses_diskstats() {
+        let disks = parse_diskstats(DISKSTATS);
+
+        assert_eq!(disks.len(), 2);
+        let (name, sda) = &disks[0];
+        assert_eq!(name, "sda");
+        assert_eq!(sda.reads, 4710);
+        assert_eq!(sda.sectors_read, 390262);
+        assert_eq!(sda.read_ms, 3160);
+        assert_eq!(sda.writes, 2561);
+        assert_eq!(sda.sectors_written, 208344);
+        assert_eq!(sda.write_ms, 5730);
+        assert_eq!(sda.in_flight, 0);
+        assert_eq!(sda.io_ms, 6110);
+        assert_eq!(sda.weighted_io_ms, 9170);
+        assert_eq!(disks[1].0, "sda1");
+    }
+
+    #[test]
+    fn computes_disk_activity() {
+        let prev = DiskCounters::default();
+        let new = DiskCounters {
+            reads: 10,
+            sectors_read: 2000,
+            read_ms: 50,
+            writes: 4,
+            sectors_written: 80,
+            write_ms: 40,
+            in_flight: 1,
+            io_ms: 500,
+            weighted_io_ms: 3000,
+        };
+        let disk = disk_activity(&new, &prev, 2.0);
+
+        assert_eq!(disk.reads_per_sec, 5.0);
+        assert_eq!(disk.read_bytes_per_sec, 2000.0 * 512.0 / 2.0);
+        assert_eq!(disk.write_bytes_per_sec, 80.0 * 512.0 / 2.0);
+        assert_eq!(disk.read_await_ms, 5.0);
+        assert_eq!(disk.write_await_ms, 10.0);
+        assert_eq!(disk.queue_depth, 1.5);
+        assert_eq!(disk.utilization_percent, 25.0);
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use procfs::process::Process;
+
+use crate::core::source::DataSource;
+
+// Kernel and virtual filesystems that hold no user data.
+const PSEUDO_FILESYSTEMS: [&str; 22] = [
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tracefs",
+    "fuse.gvfsd-fuse",
+    "fuse.portal",
+];
+
+// Filesystems kept in memory, which can be hidden separately.
+const MEMORY_FILESYSTEMS: [&str; 3] = ["tmpfs", "devtmpfs", "ramfs"];
+
+// Filesystems served over the network, whose statvfs blocks for as long as the server does not
+// answer.
+const NETWORK_FILESYSTEMS: [&str; 12] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "fuse.sshfs",
+    "fuse.s3fs",
+];
+
+// How long statvfs may take on a network filesystem before it is left to finish in the
+// background.
+const NETWORK_STATVFS_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Size and usage of a mounted filesystem, as df shows them.
+#[derive(Debug, Clone, Default)]
+pub struct FilesystemData {
+    pub mount_point: PathBuf,
+
+    // Device or other source the filesystem was mounted from, e.g. /dev/sda1.
+    pub source: String,
+
+    pub fs_type: String,
+
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+
+    // Space available to unprivileged users, which excludes the blocks reserved for root.
+    pub available_bytes: u64,
+
+    pub total_inodes: u64,
+    pub used_inodes: u64,
+}
+
+impl FilesystemData {
+    // Percentage of the space usable by unprivileged users that is used, like df's Use%.
+    pub fn used_percent(&self) -> f64 {
+        percent(self.used_bytes, self.used_bytes + self.available_bytes)
+    }
+
+    pub fn used_inodes_percent(&self) -> f64 {
+        percent(self.used_inodes, self.total_inodes)
+    }
+
+    // Whether the filesystem is kept in memory, like tmpfs.
+    pub fn is_memory_backed(&self) -> bool {
+        MEMORY_FILESYSTEMS.contains(&self.fs_type.as_str())
+    }
+}
+
+// Network mounts whose statvfs did not return in time, keyed by mount point, with the
+// channel their call will report on when it does.
+pub type PendingStatvfs = HashMap<PathBuf, Receiver<Option<FilesystemData>>>;
+
+// Reads every mounted filesystem of the current mount namespace, except pseudo filesystems.
+// A device mounted more than once (e.g. bind mounts) is only listed at its first mount point,
+// and mounts hidden by a later mount on the same path are skipped. Network filesystems whose
+// server does not answer are left out until it does, see `statvfs_network`.
+pub fn read_filesystems(
+    source: &dyn DataSource,
+    pending: &mut PendingStatvfs,
+) -> Vec<FilesystemData> {
+    let mounts = match Process::new_with_root(source.proc_path("self")).and_then(|p| p.mountinfo())
+    {
+        Ok(mounts) => mounts,
+        Err(_) => return Vec::new(),
+    };
+
+    // Index of the last mount on every path, the only one visible there
+    let visible: HashMap<&PathBuf, usize> = mounts
+        .iter()
+        .enumerate()
+        .map(|(idx, mount)| (&mount.mount_point, idx))
+        .collect();
+
+    let mut seen_devices = HashSet::new();
+    let filesystems = mounts
+        .iter()
+        .enumerate()
+        .filter(|(idx, mount)| visible[&mount.mount_point] == *idx)
+        .map(|(_, mount)| mount)
+        .filter(|mount| !PSEUDO_FILESYSTEMS.contains(&mount.fs_type.as_str()))
+        .filter(|mount| seen_devices.insert(mount.majmin.as_str()))
+        .filter_map(|mount| {
+            let mut filesystem = if NETWORK_FILESYSTEMS.contains(&mount.fs_type.as_str()) {
+                statvfs_network(&mount.mount_point, pending)?
+            } else {
+                statvfs(&mount.mount_point)?
+            };
+            filesystem.source = mount.mount_source.clone().unwrap_or_default();
+            filesystem.fs_type = mount.fs_type.clone();
+            filesystem.mount_point = mount.mount_point.clone();
+            Some(filesystem)
+        })
+        .collect();
+
+    // Forget the calls on filesystems unmounted since
+    pending.retain(|mount_point, _| visible.contains_key(mount_point));
+    filesystems
+}
+
+// Calls statvfs on a thread of its own, giving up on it after NETWORK_STATVFS_TIMEOUT so that
+// a hung server cannot hold up sampling. A call that timed out is left running, and no other
+// is started on the mount until it returns.
+fn statvfs_network(mount_point: &Path, pending: &mut PendingStatvfs) -> Option<FilesystemData> {
+    if let Some(rx) = pending.get(mount_point) {
+        return match rx.try_recv() {
+            Ok(filesystem) => {
+                pending.remove(mount_point);
+                filesystem
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                pending.remove(mount_point);
+                None
+            }
+        };
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let path = mount_point.to_path_buf();
+    thread::spawn(move || {
+        let _ = tx.send(statvfs(&path));
+    });
+    match rx.recv_timeout(NETWORK_STATVFS_TIMEOUT) {
+        Ok(filesystem) => filesystem,
+        Err(RecvTimeoutError::Timeout) => {
+            pending.insert(mount_point.to_path_buf(), rx);
+            None
+        }
+        Err(RecvTimeoutError::Disconnected) => None,
+    }
+}
+
+fn statvfs(path: &Path) -> Option<FilesystemData> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    let block_size = stat.f_frsize as u64;
+    let total_bytes = stat.f_blocks as u64 * block_size;
+    let free_bytes = stat.f_bfree as u64 * block_size;
+    Some(FilesystemData {
+        total_bytes,
+        used_bytes: total_bytes.saturating_sub(free_bytes),
+        available_bytes: stat.f_bavail as u64 * block_size,
+        total_inodes: stat.f_files as u64,
+        used_inodes: (stat.f_files as u64).saturating_sub(stat.f_ffree as u64),
+        ..Default::default()
+    })
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_a_hung_statvfs_to_return() {
+        let root = Path::new("/");
+        let (tx, rx) = mpsc::channel();
+        let mut pending = PendingStatvfs::from([(root.to_path_buf(), rx)]);
+
+        // Still hung: the mount is left out and no other call is started
+        assert!(statvfs_network(root, &mut pending).is_none());
+        assert!(pending.contains_key(root));
+
+        let answer = FilesystemData {
+            total_bytes: 1,
+            ..Default::default()
+        };
+        tx.send(Some(answer)).unwrap();
+        let filesystem = statvfs_network(root, &mut pending);
+        assert_eq!(filesystem.map(|fs| fs.total_bytes), Some(1));
+        assert!(pending.is_empty());
+
+        // Answering in time
+        assert!(statvfs_network(root, &mut pending).is_some());
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+use std::cmp::Ordering::Equal;
+use std::collections::HashMap;
+
+use crate::core::source::DataSource;
+
+const INTERRUPTS_PATH: &str = "interrupts";
+const SOFTIRQS_PATH: &str = "softirqs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrqKind {
+    // Hardware and architecture interrupts, from /proc/interrupts.
+    Hard,
+    // Softirqs such as NET_RX or TIMER, from /proc/softirqs.
+    Soft,
+}
+
+impl IrqKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            IrqKind::Hard => "irq",
+            IrqKind::Soft => "softirq",
+        }
+    }
+}
+
+// One line of /proc/interrupts or /proc/softirqs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IrqCounts {
+    // IRQ number (e.g. 24), architecture interrupt (e.g. LOC) or softirq type (e.g. NET_RX).
+    pub name: String,
+
+    // Interrupt chip, trigger type and devices of a hardware interrupt. Empty for softirqs.
+    pub description: String,
+
+    // Count on each CPU since boot, in the order of the CPUs in the table header.
+    pub per_cpu: Vec<u64>,
+}
+
+// Interrupts of one source since the previous sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrqData {
+    pub kind: IrqKind,
+    pub name: String,
+    pub description: String,
+
+    // Interrupts per second on each CPU, in the order of InterruptData's cpus.
+    pub per_cpu_per_sec: Vec<f64>,
+    pub total_per_sec: f64,
+
+    // Interrupts on every CPU since boot.
+    pub total: u64,
+}
+
+impl IrqData {
+    // Position of the CPU handling most of this source's interrupts, and its share of them in
+    // percent. None when there were none.
+    pub fn busiest_cpu(&self) -> Option<(usize, f64)> {
+        if self.total_per_sec <= 0.0 {
+            return None;
+        }
+        self.per_cpu_per_sec
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Equal))
+            .map(|(idx, rate)| (idx, rate / self.total_per_sec * 100.0))
+    }
+}
+
+// Interrupt and softirq rates per CPU.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterruptData {
+    // Numbers of the online CPUs, as listed in the header of /proc/interrupts (or of
+    // /proc/softirqs if the former cannot be read).
+    pub cpus: Vec<usize>,
+    pub irqs: Vec<IrqData>,
+}
+
+// Reads /proc/interrupts and /proc/softirqs and computes per-CPU rates from the counts of the
+// previous sample, which are replaced by the new ones. /proc/softirqs lists every possible CPU
+// while /proc/interrupts only lists the online ones, so counts are matched by CPU number and
+// the softirqs of offline CPUs are left out.
+pub fn read_interrupt_data(
+    source: &dyn DataSource,
+    counters: &mut HashMap<(IrqKind, String), Vec<u64>>,
+    elapsed_secs: Option<f64>,
+) -> InterruptData {
+    let tables: Vec<(IrqKind, Vec<usize>, Vec<IrqCounts>)> = [
+        (IrqKind::Hard, INTERRUPTS_PATH),
+        (IrqKind::Soft, SOFTIRQS_PATH),
+    ]
+    .into_iter()
+    .filter_map(|(kind, path)| {
+        let (cpus, lines) = parse_irq_table(&source.read_proc(path).ok()?);
+        Some((kind, cpus, lines))
+    })
+    .collect();
+
+    let mut data = InterruptData {
+        cpus: tables
+            .first()
+            .map(|(_, cpus, _)| cpus.clone())
+            .unwrap_or_default(),
+        irqs: Vec::new(),
+    };
+    let mut new_counters = HashMap::with_capacity(counters.len());
+
+    for (kind, cpus, lines) in tables {
+        for line in lines {
+            // Counts indexed by CPU number
+            let mut by_cpu = vec![0; cpus.iter().max().map_or(0, |max| max + 1)];
+            for (cpu, count) in cpus.iter().zip(&line.per_cpu) {
+                by_cpu[*cpu] = *count;
+            }
+
+            let key = (kind, line.name);
+            let per_cpu_per_sec: Vec<f64> = match (counters.get(&key), elapsed_secs) {
+                (Some(prev), Some(secs)) if secs > 0.0 => data
+                    .cpus
+                    .iter()
+                    .map(|cpu| {
+                        let count = by_cpu.get(*cpu).copied().unwrap_or(0);
+                        let prev = prev.get(*cpu).copied().unwrap_or(0);
+                        count.saturating_sub(prev) as f64 / secs
+                    })
+                    .collect(),
+                _ => vec![0.0; data.cpus.len()],
+            };
+
+            data.irqs.push(IrqData {
+                kind,
+                name: key.1.clone(),
+                description: line.description,
+                total_per_sec: per_cpu_per_sec.iter().sum(),
+                per_cpu_per_sec,
+                total: line.per_cpu.iter().sum(),
+            });
+            new_counters.insert(key, by_cpu);
+        }
+    }
+
+    *counters = new_counters;
+    data
+}
+
+// Parses /proc/interrupts or /proc/softirqs into the CPU numbers of the header and one entry
+// per line:
+//              CPU0       CPU1
+//     24:         12          0  IO-APIC   5-edge      ACPI:Ged
+//    LOC:     181344     179811   Local timer interrupts
+//    ERR:          0
+// Lines such as ERR and MIS have a single system-wide count, kept as the count of the first CPU.
+pub fn parse_irq_table(contents: &str) -> (Vec<usize>, Vec<IrqCounts>) {
+    let mut lines = contents.lines();
+    let cpus: Vec<usize> = match lines.next() {
+        Some(header) => header
+            .split_whitespace()
+            .filter_map(|cpu| cpu.strip_prefix("CPU")?.parse().ok())
+            .collect(),
+        None => return (Vec::new(), Vec::new()),
+    };
+
+    let irqs = lines
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            let mut fields = rest.split_whitespace().peekable();
+            let mut per_cpu = Vec::with_capacity(cpus.len());
+            while per_cpu.len() < cpus.len() {
+                match fields.peek().and_then(|field| field.parse::<u64>().ok()) {
+                    Some(count) => {
+                        per_cpu.push(count);
+                        fields.next();
+                    }
+                    None => break,
+                }
+            }
+            per_cpu.resize(cpus.len(), 0);
+
+            Some(IrqCounts {
+                name: name.trim().to_string(),
+                description: fields.collect::<Vec<_>>().join(" "),
+                per_cpu,
+            })
+        })
+        .collect();
+
+    (cpus, irqs)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqSortColumn {
+    // Rate on every CPU.
+    Total,
+    // Rate on the CPU at the given position of InterruptData's cpus.
+    Cpu(usize),
+    // IRQ numbers in numeric order, then named interrupts alphabetically.
+    Name,
+}
+
+// Sorts interrupt sources by the given column. Ties are broken by the total rate.
+pub fn sort_irqs(irqs: &mut [IrqData], column: IrqSortColumn, descending: bool) {
+    irqs.sort_by(|a, b| compare_irqs(a, b, column, descending));
+}
+
+// Order of two sources in a list sorted by the given column, as `sort_irqs` sorts them.
+pub fn compare_irqs(a: &IrqData, b: &IrqData, column: IrqSortColumn, descending: bool) -> Ordering {
+    fn by_key(a: f64, b: f64) -> Ordering {
+        a.partial_cmp(&b).unwrap_or(Equal)
+    }
+
+    let ordering = match column {
+        IrqSortColumn::Total => by_key(a.total_per_sec, b.total_per_sec),
+        IrqSortColumn::Cpu(idx) => {
+            let rate = |irq: &IrqData| irq.per_cpu_per_sec.get(idx).copied().unwrap_or(0.0);
+            by_key(rate(a), rate(b))
+        }
+        IrqSortColumn::Name => {
+            let number = |irq: &IrqData| irq.name.parse::<u64>().ok();
+            match (number(a), number(b)) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.name.cmp(&b.name),
+            }
+        }
+    }
+    .then_with(|| by_key(a.total_per_sec, b.total_per_sec));
+
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::source::RootedSource;
+    use std::path::PathBuf;
+
+    const INTERRUPTS: &str = "           CPU0       CPU1
+  24:         12          0  IO-APIC   5-edge      ACPI:Ged
+ LOC:     181344     179811   Local timer interrupts
+ ERR:          0
+";
+
+    #[test]
+    fn parses_irq_table() {
+        let (cpus, irqs) = parse_irq_table(INTERRUPTS);
+
+        assert_eq!(cpus, vec![0, 1]);
+        assert_eq!(
+            irqs,
+            vec![
+                IrqCounts {
+                    name: "24".to_string(),
+                    description: "IO-APIC 5-edge ACPI:Ged".to_string(),
+                    per_cpu: vec![12, 0],
+                },
+                IrqCounts {
+                    name: "LOC".to_string(),
+                    description: "Local timer interrupts".to_string(),
+                    per_cpu: vec![181344, 179811],
+                },
+                IrqCounts {
+                    name: "ERR".to_string(),
+                    description: String::new(),
+                    per_cpu: vec![0, 0],
+                },
+            ]
+        );
+    }
+
+    // CPU1 and CPU3 are offline: /proc/interrupts skips them, /proc/softirqs does not
+    #[test]
+    fn matches_counts_by_cpu_number() {
+        let proc = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cpu-gap");
+        let source = RootedSource::new(proc, "/nonexistent");
+        let mut counters = HashMap::from([
+            ((IrqKind::Hard, "24".to_string()), vec![0, 0, 20]),
+            ((IrqKind::Soft, "NET_RX".to_string()), vec![5, 0, 0, 0]),
+        ]);
+
+        let data = read_interrupt_data(&source, &mut counters, Some(2.0));
+
+        assert_eq!(data.cpus, [0, 2]);
+        let rates: Vec<(&str, Vec<f64>, u64)> = data
+            .irqs
+            .iter()
+            .map(|irq| (irq.name.as_str(), irq.per_cpu_per_sec.clone(), irq.total))
+            .collect();
+        assert_eq!(
+            rates,
+            [
+                ("24", vec![5.0, 5.0], 40),
+                ("LOC", vec![0.0, 0.0], 400),
+                ("HI", vec![0.0, 0.0], 4),
+                ("NET_RX", vec![2.5, 15.0], 100),
+            ]
+        );
+        assert_eq!(counters[&(IrqKind::Hard, "24".to_string())], [10, 0, 30]);
+        assert_eq!(
+            counters[&(IrqKind::Soft, "NET_RX".to_string())],
+            [10, 20, 30, 40]
+        );
+    }
+
+    #[test]
+    fn empty_table_has_no_cpus() {
+        assert_eq!(parse_irq_table(""), (Vec::new(), Vec::new()));
+    }
+
+    fn irq(name: &str, per_cpu_per_sec: Vec<f64>) -> IrqData {
+        IrqData {
+            kind: IrqKind::Hard,
+            name: name.to_string(),
+            description: String::new(),
+            total_per_sec: per_cpu_per_sec.iter().sum(),
+            per_cpu_per_sec,
+            total: 0,
+        }
+    }
+
+    fn names(irqs: &[IrqData]) -> Vec<&str> {
+        irqs.iter().map(|irq| irq.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_irqs() {
+        let mut irqs = vec![
+            irq("LOC", vec![50.0, 50.0]),
+            irq("10", vec![30.0, 0.0]),
+            irq("9", vec![0.0, 5.0]),
+        ];
+
+        sort_irqs(&mut irqs, IrqSortColumn::Total, true);
+        assert_eq!(names(&irqs), ["LOC", "10", "9"]);
+
+        sort_irqs(&mut irqs, IrqSortColumn::Cpu(1), true);
+        assert_eq!(names(&irqs), ["LOC", "9", "10"]);
+
+        sort_irqs(&mut irqs, IrqSortColumn::Name, false);
+        assert_eq!(names(&irqs), ["9", "10", "LOC"]);
+    }
+
+    #[test]
+    fn finds_busiest_cpu() {
+        assert_eq!(irq("24", vec![25.0, 75.0]).busiest_cpu(), Some((1, 75.0)));
+        assert_eq!(irq("24", vec![0.0, 0.0]).busiest_cpu(), None);
+    }
+}
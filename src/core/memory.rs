@@ -0,0 +1,261 @@
+use procfs::process::Process;
+use std::io;
+
+use crate::core::error::RTopError;
+use crate::core::source::DataSource;
+
+const MEMINFO_PATH: &str = "meminfo";
+
+// System-wide memory and swap usage from /proc/meminfo, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemMemory {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+
+    // Memory available for new allocations without swapping, including reclaimable caches.
+    pub available_bytes: u64,
+
+    pub buffers_bytes: u64,
+
+    // Page cache, not counting swap cache.
+    pub cached_bytes: u64,
+
+    // File pages waiting to be written back, and being written back, to disk.
+    pub dirty_bytes: u64,
+    pub writeback_bytes: u64,
+
+    // Kernel slab allocations, of which `reclaimable_slab_bytes` can be freed under pressure.
+    pub slab_bytes: u64,
+    pub reclaimable_slab_bytes: u64,
+
+    // Shared memory and tmpfs pages.
+    pub shmem_bytes: u64,
+
+    pub swap_total_bytes: u64,
+    pub swap_free_bytes: u64,
+
+    // Pages both in swap and in memory.
+    pub swap_cached_bytes: u64,
+
+    // Number of pages in the huge page pool, and how many of them are free.
+    pub huge_pages_total: u64,
+    pub huge_pages_free: u64,
+    pub huge_page_size_bytes: u64,
+}
+
+impl SystemMemory {
+    // Memory that cannot be reclaimed without swapping.
+    pub fn used_bytes(&self) -> u64 {
+        self.total_bytes.saturating_sub(self.available_bytes)
+    }
+
+    pub fn swap_used_bytes(&self) -> u64 {
+        self.swap_total_bytes.saturating_sub(self.swap_free_bytes)
+    }
+}
+
+pub fn read_meminfo(source: &dyn DataSource) -> io::Result<SystemMemory> {
+    Ok(parse_meminfo(&source.read_proc(MEMINFO_PATH)?))
+}
+
+pub fn parse_meminfo(contents: &str) -> SystemMemory {
+    let mut memory = SystemMemory::default();
+    let mut available = None;
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let key = match fields.next() {
+            Some(key) => key.trim_end_matches(':'),
+            None => continue,
+        };
+        let value = match fields.next().and_then(|v| v.parse::<u64>().ok()) {
+            Some(value) => value,
+            None => continue,
+        };
+        // Everything is in kB except the HugePages_* page counts
+        let bytes = match fields.next() {
+            Some("kB") => value * 1024,
+            _ => value,
+        };
+
+        match key {
+            "MemTotal" => memory.total_bytes = bytes,
+            "MemFree" => memory.free_bytes = bytes,
+            "MemAvailable" => available = Some(bytes),
+            "Buffers" => memory.buffers_bytes = bytes,
+            "Cached" => memory.cached_bytes = bytes,
+            "SwapCached" => memory.swap_cached_bytes = bytes,
+            "Dirty" => memory.dirty_bytes = bytes,
+            "Writeback" => memory.writeback_bytes = bytes,
+            "Slab" => memory.slab_bytes = bytes,
+            "SReclaimable" => memory.reclaimable_slab_bytes = bytes,
+            "Shmem" => memory.shmem_bytes = bytes,
+            "SwapTotal" => memory.swap_total_bytes = bytes,
+            "SwapFree" => memory.swap_free_bytes = bytes,
+            "HugePages_Total" => memory.huge_pages_total = bytes,
+            "HugePages_Free" => memory.huge_pages_free = bytes,
+            "Hugepagesize" => memory.huge_page_size_bytes = bytes,
+            _ => {}
+        }
+    }
+
+    // Kernels older than 3.14 have no MemAvailable; estimate it the way free(1) used to
+    memory.available_bytes = available.unwrap_or(
+        memory.free_bytes
+            + memory.buffers_bytes
+            + memory.cached_bytes
+            + memory.reclaimable_slab_bytes,
+    );
+
+    memory
+}
+
+// Breakdown of the memory used by a process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcMemory {
+    // Unique set size: pages mapped only by this process.
+    pub uss_bytes: Option<u64>,
+
+    // Proportional set size: private pages plus an even share of every shared page.
+    pub pss_bytes: Option<u64>,
+
+    // Resident pages shared with other processes.
+    pub shared_bytes: Option<u64>,
+
+    // Resident anonymous (not file backed) pages.
+    pub anon_bytes: Option<u64>,
+
+    // Pages swapped out to disk.
+    pub swap_bytes: Option<u64>,
+}
+
+// Reads /proc/<pid>/smaps_rollup, which requires ptrace access to the process. When it cannot be
+// read, the breakdown is estimated from the world-readable /proc/<pid>/statm, which carries no
+// USS, PSS or swap information.
+pub fn read_proc_memory(source: &dyn DataSource, proc: &Process) -> ProcMemory {
+    match source.read_proc(&format!("{}/smaps_rollup", proc.pid)) {
+        Ok(contents) if !contents.is_empty() => parse_smaps_rollup(&contents),
+        _ => read_statm_memory(proc).unwrap_or_default(),
+    }
+}
+
+pub fn parse_smaps_rollup(contents: &str) -> ProcMemory {
+    let mut private = 0;
+    let mut shared = 0;
+    let mut pss = None;
+    let mut anon = None;
+    let mut swap = None;
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let key = match fields.next() {
+            Some(key) => key.trim_end_matches(':'),
+            None => continue,
+        };
+        let bytes = match fields.next().and_then(|v| v.parse::<u64>().ok()) {
+            Some(kb) => kb * 1024,
+            None => continue,
+        };
+
+        match key {
+            "Pss" => pss = Some(bytes),
+            "Shared_Clean" | "Shared_Dirty" => shared += bytes,
+            "Private_Clean" | "Private_Dirty" => private += bytes,
+            "Anonymous" => anon = Some(bytes),
+            "Swap" => swap = Some(bytes),
+            _ => {}
+        }
+    }
+
+    ProcMemory {
+        uss_bytes: Some(private),
+        pss_bytes: pss,
+        shared_bytes: Some(shared),
+        anon_bytes: anon,
+        swap_bytes: swap,
+    }
+}
+
+fn read_statm_memory(proc: &Process) -> Result<ProcMemory, RTopError> {
+    let statm = proc.statm()?;
+    let page_size = procfs::page_size()?;
+
+    // statm's "shared" counts resident file and shmem pages, so the rest of the resident set is
+    // anonymous memory. Which of those pages other processes map too is unknown, so neither the
+    // unique nor the proportional set size can be told.
+    let shared = statm.shared * page_size;
+    let anon = statm.resident.saturating_sub(statm.shared) * page_size;
+
+    Ok(ProcMemory {
+        uss_bytes: None,
+        pss_bytes: None,
+        shared_bytes: Some(shared),
+        anon_bytes: Some(anon),
+        swap_bytes: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_meminfo() {
+        let memory = parse_meminfo(
+            "MemTotal:       16303412 kB\n\
+             MemFree:         1093380 kB\n\
+             MemAvailable:    9517064 kB\n\
+             Buffers:          474872 kB\n\
+             Cached:          8066628 kB\n\
+             SwapCached:         1024 kB\n\
+             Shmem:            613376 kB\n\
+             SwapTotal:       2097148 kB\n\
+             SwapFree:        2096124 kB\n\
+             HugePages_Total:       4\n\
+             HugePages_Free:        3\n\
+             Hugepagesize:       2048 kB\n",
+        );
+
+        assert_eq!(memory.total_bytes, 16303412 * 1024);
+        assert_eq!(memory.available_bytes, 9517064 * 1024);
+        assert_eq!(memory.used_bytes(), (16303412 - 9517064) * 1024);
+        assert_eq!(memory.swap_used_bytes(), 1024 * 1024);
+        assert_eq!(memory.swap_cached_bytes, 1024 * 1024);
+        assert_eq!(memory.shmem_bytes, 613376 * 1024);
+        // Huge page counts are not in kB
+        assert_eq!(memory.huge_pages_total, 4);
+        assert_eq!(memory.huge_pages_free, 3);
+        assert_eq!(memory.huge_page_size_bytes, 2048 * 1024);
+    }
+
+    #[test]
+    fn estimates_available_memory_without_memavailable() {
+        let memory = parse_meminfo(
+            "MemTotal: 1000 kB\nMemFree: 100 kB\nBuffers: 20 kB\nCached: 300 kB\n\
+             SReclaimable: 50 kB\n",
+        );
+
+        assert_eq!(memory.available_bytes, 470 * 1024);
+    }
+
+    #[test]
+    fn parses_smaps_rollup() {
+        let memory = parse_smaps_rollup(
+            "55d4c9a2f000-7ffd8a1f3000 ---p 00000000 00:00 0                          [rollup]\n\
+             Rss:                4436 kB\n\
+             Pss:                1266 kB\n\
+             Shared_Clean:       3144 kB\n\
+             Shared_Dirty:         40 kB\n\
+             Private_Clean:       120 kB\n\
+             Private_Dirty:      1132 kB\n\
+             Anonymous:          1096 kB\n\
+             Swap:                 12 kB\n",
+        );
+
+        assert_eq!(memory.uss_bytes, Some(1252 * 1024));
+        assert_eq!(memory.pss_bytes, Some(1266 * 1024));
+        assert_eq!(memory.shared_bytes, Some(3184 * 1024));
+        assert_eq!(memory.anon_bytes, Some(1096 * 1024));
+        assert_eq!(memory.swap_bytes, Some(12 * 1024));
+    }
+}
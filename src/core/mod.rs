@@ -1,4 +1,23 @@
+pub mod cgroup;
+pub mod collector;
+pub mod container;
+pub mod cpu;
+pub mod disk;
 pub mod error;
+pub mod filesystem;
+pub mod interrupts;
+pub mod memory;
+pub mod namespace;
 pub mod network;
+pub mod pressure;
 pub mod process;
+pub mod record;
+pub mod sensors;
+pub mod sock_diag;
+pub mod source;
+pub mod summary;
 pub mod system_reader;
+pub mod thread;
+pub mod tree;
+pub mod users;
+pub mod vmstat;
@@ -0,0 +1,101 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+use crate::core::process::Pid;
+use crate::core::source::DataSource;
+
+// Types of namespace listed in /proc/<pid>/ns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamespaceKind {
+    Pid,
+    Net,
+    Mnt,
+    Uts,
+    Ipc,
+    User,
+    Cgroup,
+}
+
+impl NamespaceKind {
+    pub const ALL: [NamespaceKind; 7] = [
+        NamespaceKind::Pid,
+        NamespaceKind::Net,
+        NamespaceKind::Mnt,
+        NamespaceKind::Uts,
+        NamespaceKind::Ipc,
+        NamespaceKind::User,
+        NamespaceKind::Cgroup,
+    ];
+
+    // Name of the namespace's entry in /proc/<pid>/ns.
+    pub fn name(self) -> &'static str {
+        match self {
+            NamespaceKind::Pid => "pid",
+            NamespaceKind::Net => "net",
+            NamespaceKind::Mnt => "mnt",
+            NamespaceKind::Uts => "uts",
+            NamespaceKind::Ipc => "ipc",
+            NamespaceKind::User => "user",
+            NamespaceKind::Cgroup => "cgroup",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = NamespaceKind::ALL
+            .iter()
+            .position(|k| *k == self)
+            .unwrap_or(0);
+        NamespaceKind::ALL[(idx + 1) % NamespaceKind::ALL.len()]
+    }
+}
+
+// Inodes identifying the namespaces of a process. Two processes share a namespace when they
+// have the same inode for it. None when the process could not be inspected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcNamespaces {
+    pub pid: Option<u64>,
+    pub net: Option<u64>,
+    pub mnt: Option<u64>,
+    pub uts: Option<u64>,
+    pub ipc: Option<u64>,
+    pub user: Option<u64>,
+    pub cgroup: Option<u64>,
+}
+
+impl ProcNamespaces {
+    // Reads the namespaces of a process from the links in /proc/<pid>/ns.
+    pub fn read(source: &dyn DataSource, pid: Pid) -> Self {
+        let inode = |kind: NamespaceKind| {
+            fs::metadata(source.proc_path(&format!("{}/ns/{}", pid, kind.name())))
+                .ok()
+                .map(|m| m.ino())
+        };
+        ProcNamespaces {
+            pid: inode(NamespaceKind::Pid),
+            net: inode(NamespaceKind::Net),
+            mnt: inode(NamespaceKind::Mnt),
+            uts: inode(NamespaceKind::Uts),
+            ipc: inode(NamespaceKind::Ipc),
+            user: inode(NamespaceKind::User),
+            cgroup: inode(NamespaceKind::Cgroup),
+        }
+    }
+
+    pub fn get(&self, kind: NamespaceKind) -> Option<u64> {
+        match kind {
+            NamespaceKind::Pid => self.pid,
+            NamespaceKind::Net => self.net,
+            NamespaceKind::Mnt => self.mnt,
+            NamespaceKind::Uts => self.uts,
+            NamespaceKind::Ipc => self.ipc,
+            NamespaceKind::User => self.user,
+            NamespaceKind::Cgroup => self.cgroup,
+        }
+    }
+
+    // Whether the process is in a different namespace of the given kind than `other`. False
+    // if either namespace is unknown.
+    pub fn differs_from(&self, other: &ProcNamespaces, kind: NamespaceKind) -> bool {
+        matches!((self.get(kind), other.get(kind)), (Some(a), Some(b)) if a != b)
+    }
+}
@@ -1,111 +1,301 @@
-use procfs::net;
-use procfs::net::{dev_status, TcpNetEntry, UdpNetEntry};
 use procfs::process::{FDTarget, Process};
 use std::collections::HashMap;
 
-pub enum NetEntry {
-    UDPEntry(UdpNetEntry),
-    TCPEntry(TcpNetEntry),
+use crate::core::source::DataSource;
+
+const NET_DEV_PATH: &str = "net/dev";
+const CLASS_NET_PATH: &str = "class/net";
+
+// Socket tables of the network namespace rtop runs in.
+const SOCKET_TABLES: [(&str, Protocol); 4] = [
+    ("net/tcp", Protocol::Tcp),
+    ("net/tcp6", Protocol::Tcp),
+    ("net/udp", Protocol::Udp),
+    ("net/udp6", Protocol::Udp),
+];
+
+pub type INode = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
 }
 
-impl NetEntry {
-    pub fn get_inode(&self) -> u64 {
-        match &self {
-            NetEntry::TCPEntry(entry) => entry.inode,
-            NetEntry::UDPEntry(entry) => entry.inode,
+// A socket listed in /proc/net/{tcp,tcp6,udp,udp6}.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Socket {
+    pub protocol: Protocol,
+    pub local_port: u16,
+}
+
+// Sockets by inode, which is how the fds of a process refer to them.
+pub type SocketTable = HashMap<INode, Socket>;
+
+pub fn read_socket_table(source: &dyn DataSource) -> SocketTable {
+    let mut sockets = SocketTable::new();
+    for (path, protocol) in SOCKET_TABLES {
+        if let Ok(contents) = source.read_proc(path) {
+            parse_socket_table(&contents, protocol, &mut sockets);
         }
     }
+    sockets
 }
 
-pub type INode = u64;
+// Parses /proc/net/tcp or one of its siblings into `sockets`, keeping only the local port and
+// the inode of each socket:
+//   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+//    0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 4242
+// Sockets no process holds anymore, such as those in TIME_WAIT, have inode 0 and are skipped.
+pub fn parse_socket_table(contents: &str, protocol: Protocol, sockets: &mut SocketTable) {
+    for line in contents.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let port = fields[1]
+            .rsplit_once(':')
+            .and_then(|(_, port)| u16::from_str_radix(port, 16).ok());
+        let inode = fields[9].parse::<INode>().ok().filter(|inode| *inode != 0);
+        if let (Some(local_port), Some(inode)) = (port, inode) {
+            sockets.insert(
+                inode,
+                Socket {
+                    protocol,
+                    local_port,
+                },
+            );
+        }
+    }
+}
 
-pub fn get_net_entry_map() -> HashMap<INode, NetEntry> {
-    let entries = get_tcp_net_entries()
-        .into_iter()
-        .chain(get_udp_net_entries());
-    let mut entry_map = HashMap::new();
+// Inodes of every socket the process has an fd to, including those not in the socket tables
+// (yet), such as a TCP socket that is not bound or connected.
+pub fn read_socket_inodes(proc: &Process) -> Vec<INode> {
+    let fds = match proc.fd() {
+        Ok(fds) => fds,
+        Err(_) => return Vec::new(),
+    };
+    fds.filter_map(|fd| match fd.ok()?.target {
+        FDTarget::Socket(inode) => Some(inode),
+        _ => None,
+    })
+    .collect()
+}
+
+// Returns the local TCP and UDP ports of the given sockets, together with the inodes of the TCP
+// ones so their traffic can be attributed to the process holding them.
+pub fn get_net_ports(inodes: &[INode], sockets: &SocketTable) -> (Vec<u16>, Vec<u16>, Vec<INode>) {
+    let mut tcp_ports = Vec::new();
+    let mut udp_ports = Vec::new();
+    let mut tcp_inodes = Vec::new();
 
-    for entry in entries {
-        entry_map.insert(entry.get_inode(), entry);
+    for inode in inodes {
+        match sockets.get(inode) {
+            Some(Socket {
+                protocol: Protocol::Tcp,
+                local_port,
+            }) => {
+                tcp_ports.push(*local_port);
+                tcp_inodes.push(*inode);
+            }
+            Some(Socket {
+                protocol: Protocol::Udp,
+                local_port,
+            }) => udp_ports.push(*local_port),
+            None => {}
+        }
     }
 
-    entry_map
+    (tcp_ports, udp_ports, tcp_inodes)
+}
+
+// Counters of a network interface since it came up, from /proc/net/dev.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_drops: u64,
+    pub tx_drops: u64,
 }
 
-pub fn get_net_ports(proc: &Process, entry_map: &HashMap<INode, NetEntry>) -> (Vec<u16>, Vec<u16>) {
-    use NetEntry::{TCPEntry, UDPEntry};
+// Traffic of a network interface.
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceData {
+    pub name: String,
 
-    let mut tcp_ports = Vec::new();
-    let mut udp_ports = Vec::new();
-    if let Ok(fds) = proc.fd() {
-        for fd in fds {
-            if let Ok(FDTarget::Socket(inode)) = fd.map(|v| v.target) {
-                if let Some(entry) = entry_map.get(&inode) {
-                    match entry {
-                        TCPEntry(entry) => {
-                            if entry.inode == inode {
-                                let local_addr = entry.local_address;
-                                tcp_ports.push(local_addr.port());
-                            }
-                        }
-                        UDPEntry(entry) => {
-                            if entry.inode == inode {
-                                let local_addr = entry.local_address;
-                                udp_ports.push(local_addr.port());
-                            }
-                        }
-                    }
+    // Whether the interface is the loopback or a bridge, whose traffic is also counted on
+    // other interfaces.
+    pub is_virtual: bool,
+
+    pub totals: InterfaceCounters,
+
+    // Per-second rates of bytes and packets since the last sample.
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_packets_per_sec: f64,
+    pub tx_packets_per_sec: f64,
+}
+
+// Reads the counters of every network interface, sorted by name. `counters` holds the counters
+// of the previous sample and is updated with the new ones.
+pub fn read_interface_data(
+    source: &dyn DataSource,
+    counters: &mut HashMap<String, InterfaceCounters>,
+    elapsed_secs: Option<f64>,
+) -> Vec<InterfaceData> {
+    let devices = match source.read_proc(NET_DEV_PATH) {
+        Ok(contents) => parse_net_dev(&contents),
+        Err(_) => return Vec::new(),
+    };
+
+    let mut new_counters = HashMap::with_capacity(devices.len());
+    let mut interfaces: Vec<InterfaceData> = devices
+        .into_iter()
+        .map(|(name, totals)| {
+            let mut data = InterfaceData {
+                is_virtual: is_loopback_or_bridge(source, &name),
+                totals,
+                ..Default::default()
+            };
+            if let (Some(prev), Some(secs)) = (counters.get(&name), elapsed_secs) {
+                if secs > 0.0 {
+                    let rate = |new: u64, prev: u64| new.saturating_sub(prev) as f64 / secs;
+                    data.rx_bytes_per_sec = rate(totals.rx_bytes, prev.rx_bytes);
+                    data.tx_bytes_per_sec = rate(totals.tx_bytes, prev.tx_bytes);
+                    data.rx_packets_per_sec = rate(totals.rx_packets, prev.rx_packets);
+                    data.tx_packets_per_sec = rate(totals.tx_packets, prev.tx_packets);
                 }
             }
-        }
 
-        (tcp_ports, udp_ports)
-    } else {
-        (tcp_ports, udp_ports)
-    }
+            new_counters.insert(name.clone(), totals);
+            data.name = name;
+            data
+        })
+        .collect();
+
+    *counters = new_counters;
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
 }
 
-pub fn get_system_network_stats() -> (u64, u64) {
-    let mut recv_bytes = 0;
-    let mut sent_bytes = 0;
-    match dev_status() {
-        Ok(devices) => {
-            for dev in devices {
-                let status = dev.1;
-                recv_bytes += status.recv_bytes;
-                sent_bytes += status.sent_bytes;
+// Parses /proc/net/dev, which has two header lines, then a line per interface:
+//   eth0: 1234 10 0 0 0 0 0 0 5678 12 0 0 0 0 0 0
+// with the received bytes, packets, errors, drops, fifo, frame, compressed and multicast
+// counters, then the sent bytes, packets, errors, drops, fifo, colls, carrier and compressed.
+pub fn parse_net_dev(contents: &str) -> Vec<(String, InterfaceCounters)> {
+    contents
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let values: Vec<u64> = counters
+                .split_whitespace()
+                .map(|v| v.parse().ok())
+                .collect::<Option<_>>()?;
+            if values.len() < 16 {
+                return None;
             }
-            (recv_bytes, sent_bytes)
-        }
-        Err(_) => (0, 0),
-    }
+            let counters = InterfaceCounters {
+                rx_bytes: values[0],
+                rx_packets: values[1],
+                rx_errors: values[2],
+                rx_drops: values[3],
+                tx_bytes: values[8],
+                tx_packets: values[9],
+                tx_errors: values[10],
+                tx_drops: values[11],
+            };
+            Some((name.trim().to_string(), counters))
+        })
+        .collect()
+}
+
+fn is_loopback_or_bridge(source: &dyn DataSource, name: &str) -> bool {
+    let dir = source.sys_path(CLASS_NET_PATH).join(name);
+    // ARPHRD_LOOPBACK
+    let is_loopback = std::fs::read_to_string(dir.join("type"))
+        .map(|kind| kind.trim() == "772")
+        .unwrap_or(name == "lo");
+    is_loopback || dir.join("bridge").exists()
 }
 
-// Helpers
-fn get_tcp_net_entries() -> Vec<NetEntry> {
-    use NetEntry::TCPEntry;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let tcp = net::tcp();
-    let tcp6 = net::tcp6();
+    #[test]
+    fn parses_net_dev() {
+        let devices = parse_net_dev(
+            "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    2000      20    0    0    0     0          0         0     2000      20    0    0    0     0       0          0
+  eth0: 1234 10 1 2 0 0 0 0 5678 12 3 4 0 0 0 0
+",
+        );
 
-    match (tcp, tcp6) {
-        (Ok(tcp), Ok(tcp6)) => tcp.into_iter().chain(tcp6).map(|v| TCPEntry(v)).collect(),
-        (Ok(tcp), Err(_)) => tcp.into_iter().map(|v| TCPEntry(v)).collect(),
-        (Err(_), Ok(tcp6)) => tcp6.into_iter().map(|v| TCPEntry(v)).collect(),
-        (Err(_), Err(_)) => Vec::new(),
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].0, "lo");
+        assert_eq!(
+            devices[1],
+            (
+                "eth0".to_string(),
+                InterfaceCounters {
+                    rx_bytes: 1234,
+                    rx_packets: 10,
+                    rx_errors: 1,
+                    rx_drops: 2,
+                    tx_bytes: 5678,
+                    tx_packets: 12,
+                    tx_errors: 3,
+                    tx_drops: 4,
+                }
+            )
+        );
     }
-}
 
-fn get_udp_net_entries() -> Vec<NetEntry> {
-    use NetEntry::UDPEntry;
+    #[test]
+    fn parses_socket_tables() {
+        let mut sockets = SocketTable::new();
+        parse_socket_table(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 4242 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:9C40 0100007F:1F90 06 00000000:00000000 03:00000DA5 00000000     0        0 0 3 0000000000000000
+",
+            Protocol::Tcp,
+            &mut sockets,
+        );
+        parse_socket_table(
+            "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  310: 00000000000000000000000000000000:14E9 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 5353 2 0000000000000000 0
+",
+            Protocol::Udp,
+            &mut sockets,
+        );
+
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(
+            sockets[&4242],
+            Socket {
+                protocol: Protocol::Tcp,
+                local_port: 8080,
+            }
+        );
+        assert_eq!(sockets[&5353].local_port, 5353);
+        assert_eq!(
+            get_net_ports(&[5353, 4242, 1], &sockets),
+            (vec![8080], vec![5353], vec![4242])
+        );
+    }
 
-    let udp = net::udp();
-    let udp6 = net::udp6();
+    #[test]
+    fn skips_malformed_lines() {
+        let devices = parse_net_dev("header\nheader\n  eth0: 1 2 3\nbroken line\n");
 
-    match (udp, udp6) {
-        (Ok(udp), Ok(udp6)) => udp.into_iter().chain(udp6).map(|v| UDPEntry(v)).collect(),
-        (Ok(udp), Err(_)) => udp.into_iter().map(|v| UDPEntry(v)).collect(),
-        (Err(_), Ok(udp6)) => udp6.into_iter().map(|v| UDPEntry(v)).collect(),
-        (Err(_), Err(_)) => Vec::new(),
+        assert!(devices.is_empty());
     }
 }
@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::Path;
+
+use crate::core::source::DataSource;
+
+const PRESSURE_PATH: &str = "pressure";
+
+// One line of a PSI file: the share of time tasks were stalled on a resource, as averages over
+// the last 10, 60 and 300 seconds in percent, and the total stall time in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureLine {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    pub total_usec: u64,
+}
+
+// Pressure on one resource. `some` is the time at least one task was stalled on it, `full` the
+// time every non-idle task was at once. Older kernels have no full line for cpu.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pressure {
+    pub some: PressureLine,
+    pub full: Option<PressureLine>,
+}
+
+// Pressure on CPU, memory and I/O, system-wide or for a cgroup. None for resources whose PSI
+// file is missing, e.g. on kernels built without CONFIG_PSI.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureData {
+    pub cpu: Option<Pressure>,
+    pub memory: Option<Pressure>,
+    pub io: Option<Pressure>,
+}
+
+impl PressureData {
+    pub fn is_available(&self) -> bool {
+        self.cpu.is_some() || self.memory.is_some() || self.io.is_some()
+    }
+}
+
+// Reads the system-wide pressure from /proc/pressure.
+pub fn read_system_pressure(source: &dyn DataSource) -> PressureData {
+    let dir = source.proc_path(PRESSURE_PATH);
+    PressureData {
+        cpu: read_pressure(&dir.join("cpu")),
+        memory: read_pressure(&dir.join("memory")),
+        io: read_pressure(&dir.join("io")),
+    }
+}
+
+// Reads the pressure of a cgroup v2 from its cpu.pressure, memory.pressure and io.pressure.
+pub fn read_cgroup_pressure(dir: &Path) -> PressureData {
+    PressureData {
+        cpu: read_pressure(&dir.join("cpu.pressure")),
+        memory: read_pressure(&dir.join("memory.pressure")),
+        io: read_pressure(&dir.join("io.pressure")),
+    }
+}
+
+// Parses a PSI file:
+//   some avg10=0.12 avg60=0.05 avg300=0.01 total=123456
+//   full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+pub fn parse_pressure(contents: &str) -> Option<Pressure> {
+    let mut some = None;
+    let mut full = None;
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let mut values = PressureLine::default();
+        for field in fields {
+            if let Some((key, value)) = field.split_once('=') {
+                match key {
+                    "avg10" => values.avg10 = value.parse().unwrap_or(0.0),
+                    "avg60" => values.avg60 = value.parse().unwrap_or(0.0),
+                    "avg300" => values.avg300 = value.parse().unwrap_or(0.0),
+                    "total" => values.total_usec = value.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+        match kind {
+            Some("some") => some = Some(values),
+            Some("full") => full = Some(values),
+            _ => {}
+        }
+    }
+
+    Some(Pressure { some: some?, full })
+}
+
+fn read_pressure(path: &Path) -> Option<Pressure> {
+    parse_pressure(&fs::read_to_string(path).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_some_and_full_lines() {
+        let pressure = parse_pressure(
+            "some avg10=1.53 avg60=0.87 avg300=0.20 total=123456\n\
+             full avg10=0.50 avg60=0.10 avg300=0.00 total=4567\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            pressure.some,
+            PressureLine {
+                avg10: 1.53,
+                avg60: 0.87,
+                avg300: 0.20,
+                total_usec: 123456,
+            }
+        );
+        assert_eq!(pressure.full.map(|full| full.total_usec), Some(4567));
+    }
+
+    #[test]
+    fn full_line_is_optional() {
+        let pressure = parse_pressure("some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").unwrap();
+
+        assert_eq!(pressure.full, None);
+    }
+
+    #[test]
+    fn some_line_is_required() {
+        assert_eq!(
+            parse_pressure("full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n"),
+            None
+        );
+        assert_eq!(parse_pressure(""), None);
+    }
+}
@@ -1,14 +1,46 @@
-use procfs::process::{self, Process, Stat};
-use std::cmp::Ordering::Equal;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use procfs::process::{Process, Stat};
+use rayon::prelude::*;
+use std::cmp::Ordering::{self, Equal};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use sysinfo::ProcessStatus;
 
+use crate::core::cgroup::get_unified_cgroup;
+use crate::core::container::{detect_container, ContainerInfo};
+use crate::core::cpu::{CpuTimes, KernelStat};
 use crate::core::error::RTopError;
-use crate::core::network::{get_net_entry_map, get_net_ports, INode, NetEntry};
+use crate::core::memory::read_proc_memory;
+use crate::core::namespace::ProcNamespaces;
+use crate::core::network::{
+    get_net_ports, read_socket_inodes, read_socket_table, INode, SocketTable,
+};
+use crate::core::sock_diag::SocketBytes;
+use crate::core::source::DataSource;
+use crate::core::thread::{read_thread_data, ThreadData, ThreadSelection};
+use crate::core::users::UserResolver;
 
 pub type Pid = libc::pid_t;
 pub type Uid = libc::uid_t;
+pub type Gid = libc::gid_t;
+
+// Real, effective, saved set and filesystem IDs of a process, from /proc/<pid>/status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Credentials<T> {
+    pub real: T,
+    pub effective: T,
+    pub saved: T,
+    pub filesystem: T,
+}
+
+impl<T: PartialEq> Credentials<T> {
+    // Whether the process runs with IDs other than its real one, as setuid/setgid programs do.
+    pub fn is_elevated(&self) -> bool {
+        self.effective != self.real || self.saved != self.real
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct ProcData {
@@ -24,18 +56,63 @@ pub struct ProcData {
     // Memory usage as a percentage.
     pub mem_usage_percent: f64,
 
+    // Unique set size in bytes: memory that would be freed if the process exited.
+    pub uss_bytes: Option<u64>,
+
+    // Proportional set size in bytes: private memory plus a fair share of shared memory.
+    pub pss_bytes: Option<u64>,
+
+    // Resident memory shared with other processes, in bytes.
+    pub shared_mem_bytes: Option<u64>,
+
+    // Resident anonymous memory, in bytes.
+    pub anon_mem_bytes: Option<u64>,
+
+    // Memory swapped out to disk, in bytes.
+    pub swap_bytes: Option<u64>,
+
     // Total number of bytes read by the process on disk.
     pub total_disk_read_bytes: Option<u64>,
 
     // Total number of bytes written by the process on disk.
     pub total_disk_write_bytes: Option<u64>,
 
-    // Total number of bytes received by the process over the network.
+    // Bytes per second read by the process from disk since the last sample.
+    pub disk_read_bytes_per_sec: f64,
+
+    // Bytes per second written by the process to disk since the last sample.
+    pub disk_write_bytes_per_sec: f64,
+
+    // Total number of bytes the process passed to read-like syscalls (rchar), including cache hits.
+    pub total_read_chars: Option<u64>,
+
+    // Total number of bytes the process passed to write-like syscalls (wchar).
+    pub total_write_chars: Option<u64>,
+
+    // Total number of read-like syscalls made by the process.
+    pub total_read_syscalls: Option<u64>,
+
+    // Total number of write-like syscalls made by the process.
+    pub total_write_syscalls: Option<u64>,
+
+    // Per-second rates of rchar, wchar, syscr and syscw since the last sample.
+    pub read_chars_per_sec: f64,
+    pub write_chars_per_sec: f64,
+    pub read_syscalls_per_sec: f64,
+    pub write_syscalls_per_sec: f64,
+
+    // Total number of bytes received by the process over its open TCP sockets.
     pub total_net_received_bytes: u64,
 
-    // Total number of bytes sent by the process over the network.
+    // Total number of bytes sent by the process over its open TCP sockets.
     pub total_net_sent_bytes: u64,
 
+    // Bytes per second received by the process over the network since the last sample.
+    pub net_received_bytes_per_sec: f64,
+
+    // Bytes per second sent by the process over the network since the last sample.
+    pub net_sent_bytes_per_sec: f64,
+
     // Name of the process.
     pub name: String,
 
@@ -48,189 +125,660 @@ pub struct ProcData {
     // Process' user ID.
     pub uid: Option<Uid>,
 
+    // Real, effective, saved and filesystem user IDs of the process.
+    pub uids: Option<Credentials<Uid>>,
+
+    // Real, effective, saved and filesystem group IDs of the process.
+    pub gids: Option<Credentials<Gid>>,
+
+    // Name of the process' real user.
+    pub user: Option<String>,
+
+    // Name of the process' effective user.
+    pub effective_user: Option<String>,
+
+    // Name of the process' real group.
+    pub group: Option<String>,
+
     // Process' priority
     pub priority: i64,
 
+    // Time the process started, in seconds since the Unix epoch.
+    pub start_time: u64,
+
+    // Seconds elapsed since the process started.
+    pub run_time_secs: u64,
+
     // Process' tcp ports connection
     pub tcp_ports: Vec<u16>,
 
     // Process' udp ports connection
     pub udp_ports: Vec<u16>,
+
+    // CPU the process last ran on.
+    pub last_cpu: Option<i32>,
+
+    // Path of the process' cgroup v2 control group, from /proc/<pid>/cgroup.
+    pub cgroup: Option<String>,
+
+    // Container the process runs in, or None if it runs on the host.
+    pub container: Option<ContainerInfo>,
+
+    // Namespaces the process belongs to, from /proc/<pid>/ns.
+    pub namespaces: ProcNamespaces,
+
+    // PIDs of the process in each nested pid namespace it belongs to, outermost first
+    // (NSpid in /proc/<pid>/status). The last one is the PID seen inside its own namespace.
+    pub ns_pids: Vec<Pid>,
+
+    // Number of threads of the process, from /proc/<pid>/stat.
+    pub num_threads: i64,
+
+    // Threads of the process, read from /proc/<pid>/task if they were selected, else empty.
+    pub threads: Vec<ThreadData>,
+}
+
+// I/O counters of a process, as read from /proc/<pid>/io.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoCounters {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub rchar: u64,
+    pub wchar: u64,
+    pub syscr: u64,
+    pub syscw: u64,
+}
+
+// Per-second rates of the counters in IoCounters between two ticks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IoRates {
+    pub read_bytes: f64,
+    pub write_bytes: f64,
+    pub rchar: f64,
+    pub wchar: f64,
+    pub syscr: f64,
+    pub syscw: f64,
+}
+
+// Identifies a process (or thread) across ticks. Pids get recycled, but a recycled pid never
+// comes back with the same start time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcKey {
+    pub pid: Pid,
+    // Start time of the process, in clock ticks since boot.
+    pub start_time: u64,
+}
+
+impl ProcKey {
+    pub fn new(pid: Pid, stat: &Stat) -> Self {
+        ProcKey {
+            pid,
+            start_time: stat.starttime,
+        }
+    }
+}
+
+// What is read about a process when it is first seen, and kept until it exits or executes
+// another program. A process entering other namespaces without executing anything keeps
+// showing the old ones.
+#[derive(Debug, Clone, Default)]
+struct ProcInfo {
+    // Name in /proc/<pid>/stat and target of /proc/<pid>/exe when the rest was read. Executing
+    // another program changes the latter, and usually the former.
+    comm: String,
+    exe: Option<PathBuf>,
+    command: String,
+    name: String,
+    namespaces: ProcNamespaces,
+}
+
+impl ProcInfo {
+    fn read(proc: &Process, stat: &Stat, exe: Option<PathBuf>, source: &dyn DataSource) -> Self {
+        let (command, name) = get_proc_cmd_and_name(proc, stat);
+        ProcInfo {
+            comm: stat.comm.clone(),
+            exe,
+            command,
+            name,
+            namespaces: ProcNamespaces::read(source, proc.pid),
+        }
+    }
+}
+
+// User and group IDs of a process, and its pid in each nested pid namespace, from
+// /proc/<pid>/status. They are read every tick, since setuid(2) and friends change the IDs
+// without executing anything.
+#[derive(Debug, Clone, Default)]
+struct ProcIds {
+    uids: Option<Credentials<Uid>>,
+    gids: Option<Credentials<Gid>>,
+    ns_pids: Vec<Pid>,
+}
+
+impl ProcIds {
+    fn read(proc: &Process) -> Self {
+        let status = match proc.status() {
+            Ok(status) => status,
+            Err(_) => return ProcIds::default(),
+        };
+        let credentials = |ids: (u32, u32, u32, u32)| Credentials {
+            real: ids.0,
+            effective: ids.1,
+            saved: ids.2,
+            filesystem: ids.3,
+        };
+
+        ProcIds {
+            uids: Some(credentials((
+                status.ruid,
+                status.euid,
+                status.suid,
+                status.fuid,
+            ))),
+            gids: Some(credentials((
+                status.rgid,
+                status.egid,
+                status.sgid,
+                status.fgid,
+            ))),
+            ns_pids: status.nspid.unwrap_or_default(),
+        }
+    }
+}
+
+// Counters and cached data of a process kept between ticks.
+#[derive(Debug, Clone, Default)]
+struct ProcSample {
+    cpu_time: u64,
+    io: Option<IoCounters>,
+    info: Arc<ProcInfo>,
+    // Number of fds the process had open, and the sockets among them. The fds are only looked
+    // at again when their number changes, one of the sockets is closed or a socket no process
+    // held was opened.
+    fd_count: Option<usize>,
+    socket_inodes: Arc<[INode]>,
+}
+
+// Per-process state kept between ticks, keyed by (pid, start time) so that a process never
+// inherits the counters of a previous process that had the same pid.
+#[derive(Debug, Default)]
+pub struct ProcHistory {
+    procs: HashMap<ProcKey, ProcSample>,
+    threads: HashMap<ProcKey, u64>,
+    // Last TCP byte counters sock_diag dumped, and how much longer ago than the previous tick
+    // that was if the dumps failed since.
+    socket_bytes: HashMap<INode, SocketBytes>,
+    socket_bytes_age_secs: f64,
+    sockets: SocketTable,
+}
+
+// Values shared by every process sampled during the same tick.
+pub(crate) struct SampleContext<'a> {
+    pub(crate) source: &'a dyn DataSource,
+    pub(crate) cpu_usage: f64,
+    pub(crate) cpu_fraction: f64,
+    pub(crate) total_memory_bytes: u64,
+    pub(crate) use_current_cpu_total: bool,
+    sockets: &'a SocketTable,
+    prev_sockets: &'a SocketTable,
+    // Whether a socket opened since the previous tick is held by none of the processes as they
+    // were then, so it may have taken the place of another fd without changing their number.
+    unclaimed_sockets: bool,
+    socket_bytes: &'a HashMap<INode, SocketBytes>,
+    prev_socket_bytes: &'a HashMap<INode, SocketBytes>,
+    // Seconds between the dumps of `prev_socket_bytes` and `socket_bytes`, if both were made.
+    socket_bytes_secs: Option<f64>,
+    // Seconds elapsed since the previous tick, if there was one.
+    pub(crate) elapsed_secs: Option<f64>,
+    boot_time_secs: u64,
+    ticks_per_second: u64,
+    now_secs: u64,
+    users: &'a UserResolver,
+    // Namespaces of PID 1, which processes on the host share.
+    host_namespaces: ProcNamespaces,
 }
 
 impl ProcData {
     fn new(
         proc: Process,
         stat: Stat,
-        cpu_usage: f64,
-        cpu_fraction: f64,
-        prev_cpu_time: u64,
-        total_memory_bytes: u64,
-        use_current_cpu_total: bool,
-        net_map: &HashMap<INode, NetEntry>,
-    ) -> (Self, u64) {
-        let (command, name) = get_proc_cmd_and_name(&proc, &stat);
+        prev: Option<&ProcSample>,
+        threads: Vec<ThreadData>,
+        ctx: &SampleContext,
+    ) -> (Self, ProcSample) {
+        let exe = proc.exe().ok();
+        let info = match prev {
+            Some(prev) if prev.info.comm == stat.comm && prev.info.exe == exe => {
+                Arc::clone(&prev.info)
+            }
+            _ => Arc::new(ProcInfo::read(&proc, &stat, exe, ctx.source)),
+        };
+        let ids = ProcIds::read(&proc);
+
+        // A socket that left the tables was closed, even if another fd took its number
+        let fd_count = proc.fd_count().ok();
+        let socket_inodes = match prev {
+            Some(prev)
+                if !ctx.unclaimed_sockets
+                    && prev.fd_count == fd_count
+                    && prev.socket_inodes.iter().all(|inode| {
+                        ctx.sockets.contains_key(inode) || !ctx.prev_sockets.contains_key(inode)
+                    }) =>
+            {
+                Arc::clone(&prev.socket_inodes)
+            }
+            _ => read_socket_inodes(&proc).into(),
+        };
+        // A process first seen, at startup or because it just started, shows no usage until
+        // the next tick rather than all the CPU time it ever used.
         let (cpu_usage_percent, new_process_time) = get_cpu_usage(
             &stat,
-            cpu_usage,
-            cpu_fraction,
-            prev_cpu_time,
-            use_current_cpu_total,
+            ctx.cpu_usage,
+            ctx.cpu_fraction,
+            prev.map_or(stat.utime + stat.stime, |prev| prev.cpu_time),
+            ctx.use_current_cpu_total,
         );
-        let mut total_disk_read_bytes = None;
-        let mut total_disk_write_bytes = None;
+        let io = proc.io().ok().map(|io| IoCounters {
+            read_bytes: io.read_bytes,
+            write_bytes: io.write_bytes,
+            rchar: io.rchar,
+            wchar: io.wchar,
+            syscr: io.syscr,
+            syscw: io.syscw,
+        });
+        let io_rates = match (io, prev.and_then(|prev| prev.io), ctx.elapsed_secs) {
+            (Some(io), Some(prev_io), Some(secs)) => get_io_rates(&io, &prev_io, secs),
+            _ => IoRates::default(),
+        };
 
-        if let Ok(io) = proc.io() {
-            total_disk_read_bytes = Some(io.read_bytes);
-            total_disk_write_bytes = Some(io.write_bytes);
-        }
+        let mem_usage_bytes = stat.rss_bytes().unwrap_or(0);
+        // The total is 0 when meminfo could never be read
+        let mem_usage_percent = if ctx.total_memory_bytes == 0 {
+            0.0
+        } else {
+            mem_usage_bytes as f64 / ctx.total_memory_bytes as f64 * 100.0
+        };
+        let memory = read_proc_memory(ctx.source, &proc);
+
+        let start_time = ctx.boot_time_secs + stat.starttime / ctx.ticks_per_second.max(1);
+        let run_time_secs = ctx.now_secs.saturating_sub(start_time);
 
-        let mem_usage_bytes = u64::try_from(stat.rss_bytes().unwrap_or(0)).unwrap_or(0);
-        let mem_usage_percent = mem_usage_bytes as f64 / total_memory_bytes as f64 * 100.0;
+        let user_name = |uid: Uid| ctx.users.user_name(uid).map(|name| name.to_string());
 
-        let (tcp_ports, udp_ports) = get_net_ports(&proc, net_map);
+        let (tcp_ports, udp_ports, tcp_inodes) = get_net_ports(&socket_inodes, ctx.sockets);
+        let (total_net, net_rate) = get_net_usage(&tcp_inodes, ctx);
+
+        let cgroups = proc.cgroups().unwrap_or_default();
+        let container = detect_container(
+            cgroups.iter().map(|cgroup| cgroup.pathname.as_str()),
+            &info.namespaces,
+            &ctx.host_namespaces,
+        );
 
         let data = ProcData {
             pid: proc.pid,
             parent_pid: stat.ppid,
             cpu_usage_percent,
             mem_usage_percent,
+            uss_bytes: memory.uss_bytes,
+            pss_bytes: memory.pss_bytes,
+            shared_mem_bytes: memory.shared_bytes,
+            anon_mem_bytes: memory.anon_bytes,
+            swap_bytes: memory.swap_bytes,
             priority: stat.priority,
-            total_disk_read_bytes,
-            total_disk_write_bytes,
-            total_net_received_bytes: 0,
-            total_net_sent_bytes: 0,
-            name: name,
-            command: command,
+            start_time,
+            run_time_secs,
+            total_disk_read_bytes: io.map(|io| io.read_bytes),
+            total_disk_write_bytes: io.map(|io| io.write_bytes),
+            disk_read_bytes_per_sec: io_rates.read_bytes,
+            disk_write_bytes_per_sec: io_rates.write_bytes,
+            total_read_chars: io.map(|io| io.rchar),
+            total_write_chars: io.map(|io| io.wchar),
+            total_read_syscalls: io.map(|io| io.syscr),
+            total_write_syscalls: io.map(|io| io.syscw),
+            read_chars_per_sec: io_rates.rchar,
+            write_chars_per_sec: io_rates.wchar,
+            read_syscalls_per_sec: io_rates.syscr,
+            write_syscalls_per_sec: io_rates.syscw,
+            total_net_received_bytes: total_net.received,
+            total_net_sent_bytes: total_net.sent,
+            net_received_bytes_per_sec: net_rate.0,
+            net_sent_bytes_per_sec: net_rate.1,
+            name: info.name.clone(),
+            command: info.command.clone(),
             state: (ProcessStatus::from(stat.state).to_string(), stat.state),
             uid: proc.uid().ok(),
+            uids: ids.uids,
+            gids: ids.gids,
+            user: ids.uids.and_then(|uids| user_name(uids.real)),
+            effective_user: ids.uids.and_then(|uids| user_name(uids.effective)),
+            group: ids
+                .gids
+                .and_then(|gids| ctx.users.group_name(gids.real).map(|g| g.to_string())),
             tcp_ports,
             udp_ports,
+            last_cpu: stat.processor,
+            cgroup: get_unified_cgroup(&cgroups),
+            container,
+            namespaces: info.namespaces,
+            ns_pids: ids.ns_pids,
+            num_threads: stat.num_threads,
+            threads,
+        };
+
+        let sample = ProcSample {
+            cpu_time: new_process_time,
+            io,
+            info,
+            fd_count,
+            socket_inodes,
         };
 
-        (data, new_process_time)
+        (data, sample)
     }
 }
 
+// Samples every process, and the threads of those in `threads`. The processes are read on
+// rayon's worker pool, from the state kept in `history` for the previous tick, which is then
+// replaced with the new state.
 pub fn read_process_data(
-    prev_idle: &mut f64,
-    prev_non_idle: &mut f64,
-    cpu_times: &mut HashMap<Pid, u64>,
-    use_current_cpu_total: bool,
+    source: &dyn DataSource,
+    (kernel_stat, prev_kernel_stat): (&KernelStat, &KernelStat),
+    history: &mut ProcHistory,
+    elapsed_secs: Option<f64>,
+    (use_current_cpu_total, threads): (bool, &ThreadSelection),
     total_memory_bytes: u64,
+    users: &UserResolver,
 ) -> Result<Vec<ProcData>, RTopError> {
-    let mut current_pids = HashSet::new();
-
-    let net_map = get_net_entry_map();
-
-    if let Ok((cpu_usage, cpu_percentage)) = cpu_usage_calculation(prev_idle, prev_non_idle) {
-        let mut data: Vec<ProcData> = process::all_processes()?
-            .filter_map(|proc| {
-                if let Ok(proc) = proc {
-                    if let Ok(stat) = proc.stat() {
-                        let pid = proc.pid;
-                        let prev_proc_cpu_time = *cpu_times.get(&pid).unwrap_or(&0);
-                        let (data, new_proc_cpu_time) = ProcData::new(
-                            proc,
-                            stat,
-                            cpu_usage,
-                            cpu_percentage,
-                            prev_proc_cpu_time,
-                            total_memory_bytes,
-                            use_current_cpu_total,
-                            &net_map,
-                        );
-                        cpu_times.insert(pid, new_proc_cpu_time);
-                        current_pids.insert(pid);
-                        return Some(data);
-                    } else {
-                        return None;
-                    }
-                } else {
-                    return None;
-                }
-            })
-            .collect();
+    let sockets = read_socket_table(source);
+    let unclaimed_sockets = has_unclaimed_sockets(&sockets, history);
+    // When the dump fails, the totals stay at the last good one, without rates
+    let empty = HashMap::new();
+    let new_socket_bytes = source
+        .tcp_socket_bytes()
+        .map_err(|err| log::warn!("cannot read TCP socket bytes: {}", err))
+        .ok();
+    let (socket_bytes, prev_socket_bytes) = match &new_socket_bytes {
+        Some(new) => (new, &history.socket_bytes),
+        None => (&history.socket_bytes, &empty),
+    };
 
-        let all_pids: HashSet<Pid> = cpu_times.keys().map(|k| *k).collect();
-        all_pids.difference(&current_pids).for_each(|k| {
-            cpu_times.remove(&k);
-        });
+    let (cpu_usage, cpu_percentage) =
+        cpu_usage_calculation(&kernel_stat.cpu, &prev_kernel_stat.cpu);
+
+    let ctx = SampleContext {
+        source,
+        cpu_usage,
+        cpu_fraction: cpu_percentage,
+        total_memory_bytes,
+        use_current_cpu_total,
+        sockets: &sockets,
+        prev_sockets: &history.sockets,
+        unclaimed_sockets,
+        socket_bytes,
+        prev_socket_bytes,
+        socket_bytes_secs: elapsed_secs.map(|secs| secs + history.socket_bytes_age_secs),
+        elapsed_secs,
+        boot_time_secs: kernel_stat.boot_time,
+        ticks_per_second: procfs::ticks_per_second().unwrap_or(100),
+        now_secs: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        users,
+        host_namespaces: ProcNamespaces::read(source, 1),
+    };
 
-        data.sort_by(|a, b| {
-            if b.cpu_usage_percent == a.cpu_usage_percent {
-                b.mem_usage_percent
-                    .partial_cmp(&a.mem_usage_percent)
-                    .unwrap_or(Equal)
+    let samples: Vec<_> = all_processes(source)?
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|proc| {
+            let stat = proc.stat().ok()?;
+            let key = ProcKey::new(proc.pid, &stat);
+            let (proc_threads, thread_samples) = if threads.includes(proc.pid) {
+                read_thread_data(&proc, &ctx, &history.threads)
             } else {
-                b.cpu_usage_percent
-                    .partial_cmp(&a.cpu_usage_percent)
-                    .unwrap_or(Equal)
-            }
-        });
+                (Vec::new(), Vec::new())
+            };
+            let prev = history.procs.get(&key);
+            let (data, sample) = ProcData::new(proc, stat, prev, proc_threads, &ctx);
+            Some((key, data, sample, thread_samples))
+        })
+        .collect();
+
+    let mut data = Vec::with_capacity(samples.len());
+    let mut procs = HashMap::with_capacity(samples.len());
+    let mut thread_times = HashMap::with_capacity(history.threads.len());
+    for (key, proc_data, sample, thread_samples) in samples {
+        data.push(proc_data);
+        procs.insert(key, sample);
+        thread_times.extend(thread_samples);
+    }
+    let (socket_bytes, socket_bytes_age_secs) = match new_socket_bytes {
+        Some(new) => (new, 0.0),
+        None => (
+            std::mem::take(&mut history.socket_bytes),
+            history.socket_bytes_age_secs + elapsed_secs.unwrap_or(0.0),
+        ),
+    };
+    *history = ProcHistory {
+        procs,
+        threads: thread_times,
+        socket_bytes,
+        socket_bytes_age_secs,
+        sockets,
+    };
+
+    sort_processes(&mut data, SortColumn::Cpu, true);
+
+    Ok(data)
+}
+
+// Every process under the procfs root. procfs' all_processes_with_root lists the root but
+// opens what it finds under /proc, so the directory is walked here instead.
+fn all_processes(source: &dyn DataSource) -> Result<impl Iterator<Item = Process>, RTopError> {
+    let entries = fs::read_dir(source.proc_root())?;
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<Pid>().is_ok())
+        .filter_map(|entry| Process::new_with_root(entry.path()).ok()))
+}
+
+// Whether a socket that was not in the tables at the previous tick is held by no process that
+// was sampled then. A socket of a process whose fds cannot be read stays unclaimed, but only
+// makes every process be rescanned at the tick it appears.
+fn has_unclaimed_sockets(sockets: &SocketTable, history: &ProcHistory) -> bool {
+    let opened: Vec<INode> = sockets
+        .keys()
+        .filter(|inode| !history.sockets.contains_key(inode))
+        .copied()
+        .collect();
+    if opened.is_empty() {
+        return false;
+    }
+    let claimed: HashSet<INode> = history
+        .procs
+        .values()
+        .flat_map(|sample| sample.socket_inodes.iter().copied())
+        .collect();
+    opened.iter().any(|inode| !claimed.contains(inode))
+}
+
+// Columns the process list can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Pid,
+    Cpu,
+    Mem,
+    Uss,
+    Pss,
+    SharedMem,
+    AnonMem,
+    Swap,
+    RunTime,
+    User,
+    DiskRead,
+    DiskWrite,
+    DiskReadRate,
+    DiskWriteRate,
+    NetReceived,
+    NetSent,
+    Container,
+    Name,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 18] = [
+        SortColumn::Pid,
+        SortColumn::Cpu,
+        SortColumn::Mem,
+        SortColumn::Uss,
+        SortColumn::Pss,
+        SortColumn::SharedMem,
+        SortColumn::AnonMem,
+        SortColumn::Swap,
+        SortColumn::RunTime,
+        SortColumn::User,
+        SortColumn::DiskRead,
+        SortColumn::DiskWrite,
+        SortColumn::DiskReadRate,
+        SortColumn::DiskWriteRate,
+        SortColumn::NetReceived,
+        SortColumn::NetSent,
+        SortColumn::Container,
+        SortColumn::Name,
+    ];
+
+    // Name of the column on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortColumn::Pid => "pid",
+            SortColumn::Cpu => "cpu",
+            SortColumn::Mem => "mem",
+            SortColumn::Uss => "uss",
+            SortColumn::Pss => "pss",
+            SortColumn::SharedMem => "shared",
+            SortColumn::AnonMem => "anon",
+            SortColumn::Swap => "swap",
+            SortColumn::RunTime => "time",
+            SortColumn::User => "user",
+            SortColumn::DiskRead => "read",
+            SortColumn::DiskWrite => "write",
+            SortColumn::DiskReadRate => "read-rate",
+            SortColumn::DiskWriteRate => "write-rate",
+            SortColumn::NetReceived => "net-rx",
+            SortColumn::NetSent => "net-tx",
+            SortColumn::Container => "container",
+            SortColumn::Name => "name",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SortColumn::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    // Whether the column is sorted largest first by default, as usage columns are.
+    pub fn descending_by_default(self) -> bool {
+        !matches!(
+            self,
+            SortColumn::Pid | SortColumn::User | SortColumn::Container | SortColumn::Name
+        )
+    }
+}
+
+// Sorts processes by the given column. Ties are broken by memory usage, then by pid.
+pub fn sort_processes(data: &mut [ProcData], column: SortColumn, descending: bool) {
+    data.sort_by(|a, b| compare_processes(a, b, column, descending));
+}
+
+// Order of two processes in a list sorted by the given column, as `sort_processes` sorts them.
+pub fn compare_processes(
+    a: &ProcData,
+    b: &ProcData,
+    column: SortColumn,
+    descending: bool,
+) -> Ordering {
+    fn by_key<T: PartialOrd>(a: T, b: T) -> Ordering {
+        a.partial_cmp(&b).unwrap_or(Equal)
+    }
+
+    let ordering = match column {
+        SortColumn::Pid => a.pid.cmp(&b.pid),
+        SortColumn::Cpu => by_key(a.cpu_usage_percent, b.cpu_usage_percent),
+        SortColumn::Mem => by_key(a.mem_usage_percent, b.mem_usage_percent),
+        SortColumn::Uss => a.uss_bytes.cmp(&b.uss_bytes),
+        SortColumn::Pss => a.pss_bytes.cmp(&b.pss_bytes),
+        SortColumn::SharedMem => a.shared_mem_bytes.cmp(&b.shared_mem_bytes),
+        SortColumn::AnonMem => a.anon_mem_bytes.cmp(&b.anon_mem_bytes),
+        SortColumn::Swap => a.swap_bytes.cmp(&b.swap_bytes),
+        SortColumn::RunTime => a.run_time_secs.cmp(&b.run_time_secs),
+        SortColumn::User => a.user.cmp(&b.user),
+        SortColumn::DiskRead => a.total_disk_read_bytes.cmp(&b.total_disk_read_bytes),
+        SortColumn::DiskWrite => a.total_disk_write_bytes.cmp(&b.total_disk_write_bytes),
+        SortColumn::DiskReadRate => by_key(a.disk_read_bytes_per_sec, b.disk_read_bytes_per_sec),
+        SortColumn::DiskWriteRate => by_key(a.disk_write_bytes_per_sec, b.disk_write_bytes_per_sec),
+        SortColumn::NetReceived => {
+            by_key(a.net_received_bytes_per_sec, b.net_received_bytes_per_sec)
+        }
+        SortColumn::NetSent => by_key(a.net_sent_bytes_per_sec, b.net_sent_bytes_per_sec),
+        SortColumn::Container => {
+            let label = |p: &ProcData| p.container.as_ref().map(ContainerInfo::label);
+            label(a).cmp(&label(b))
+        }
+        SortColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    }
+    .then_with(|| by_key(a.mem_usage_percent, b.mem_usage_percent))
+    .then_with(|| b.pid.cmp(&a.pid));
 
-        Ok(data)
+    if descending {
+        ordering.reverse()
     } else {
-        Err(RTopError {
-            err_msg: "Could not compute CPU usage.".to_string(),
-        })
+        ordering
     }
 }
 
 const MAX_STAT_NAME_LEN: usize = 15;
 
 fn get_proc_cmd_and_name(proc: &Process, stat: &Stat) -> (String, String) {
-    let (command, name) = {
-        let truncated_name = stat.comm.as_str();
-        if let Ok(cmdline) = proc.cmdline() {
-            if cmdline.is_empty() {
-                return (format!("[{}]", truncated_name), truncated_name.to_string());
-            } else {
-                let name = if truncated_name.len() >= MAX_STAT_NAME_LEN {
-                    if let Some(first_part) = cmdline.first() {
-                        first_part
-                            .rsplit_once('/')
-                            .map(|(_prefix, suffix)| suffix)
-                            .unwrap_or(truncated_name)
-                            .to_string()
-                    } else {
-                        truncated_name.to_string()
-                    }
+    let truncated_name = stat.comm.as_str();
+    if let Ok(cmdline) = proc.cmdline() {
+        if cmdline.is_empty() {
+            (format!("[{}]", truncated_name), truncated_name.to_string())
+        } else {
+            let name = if truncated_name.len() >= MAX_STAT_NAME_LEN {
+                if let Some(first_part) = cmdline.first() {
+                    first_part
+                        .rsplit_once('/')
+                        .map(|(_prefix, suffix)| suffix)
+                        .unwrap_or(truncated_name)
+                        .to_string()
                 } else {
                     truncated_name.to_string()
-                };
+                }
+            } else {
+                truncated_name.to_string()
+            };
 
-                return (cmdline.join(" "), name);
-            }
-        } else {
-            (truncated_name.to_string(), truncated_name.to_string())
+            (cmdline.join(" "), name)
         }
-    };
-
-    return (command, name);
+    } else {
+        (truncated_name.to_string(), truncated_name.to_string())
+    }
 }
 
-fn cpu_usage_calculation(
-    prev_idle: &mut f64,
-    prev_non_idle: &mut f64,
-) -> Result<(f64, f64), RTopError> {
-    use std::io::prelude::*;
-    use std::io::BufReader;
-
-    let mut reader = BufReader::new(std::fs::File::open("/proc/stat")?);
-    let mut first_line = String::new();
-    reader.read_line(&mut first_line)?;
-
-    let (idle, non_idle) = compute_idle_and_non_idle_values(first_line);
+// Ticks the CPUs spent working since the previous sample, and the fraction of the total time
+// that represents.
+fn cpu_usage_calculation(cpu: &CpuTimes, prev_cpu: &CpuTimes) -> (f64, f64) {
+    let idle = cpu.idle_time() as f64;
+    let non_idle = cpu.busy_time() as f64;
+    let prev_idle = prev_cpu.idle_time() as f64;
+    let prev_non_idle = prev_cpu.busy_time() as f64;
 
     let total = idle + non_idle;
-    let prev_total = *prev_idle + *prev_non_idle;
+    let prev_total = prev_idle + prev_non_idle;
 
     let total_delta: f64 = total - prev_total;
-    let idle_delta: f64 = idle - *prev_idle;
-
-    *prev_idle = idle;
-    *prev_non_idle = non_idle;
+    let idle_delta: f64 = idle - prev_idle;
 
     let active_time = if total_delta - idle_delta != 0_f64 {
         total_delta - idle_delta
@@ -244,34 +792,10 @@ fn cpu_usage_calculation(
         0_f64
     };
 
-    Ok((active_time, cpu_percentage))
+    (active_time, cpu_percentage)
 }
 
-fn compute_idle_and_non_idle_values(line: String) -> (f64, f64) {
-    fn str_to_f64(val: Option<&str>) -> f64 {
-        val.and_then(|v| v.trim().parse::<f64>().ok())
-            .unwrap_or(0_f64)
-    }
-
-    let mut val = line.split_whitespace();
-    let prefix = val.next().map(|s| s.trim());
-    assert!(prefix == Some("cpu"));
-    let userm = str_to_f64(val.next());
-    let nice: f64 = str_to_f64(val.next());
-    let kernelm: f64 = str_to_f64(val.next());
-    let idle: f64 = str_to_f64(val.next());
-    let iowait: f64 = str_to_f64(val.next());
-    let irq: f64 = str_to_f64(val.next());
-    let softirq: f64 = str_to_f64(val.next());
-    let steal: f64 = str_to_f64(val.next());
-
-    let idle = idle + iowait;
-    let non_idle = userm + nice + kernelm + irq + softirq + steal;
-
-    (idle, non_idle)
-}
-
-fn get_cpu_usage(
+pub(crate) fn get_cpu_usage(
     stat: &Stat,
     cpu_usage: f64,
     cpu_percentage: f64,
@@ -279,7 +803,7 @@ fn get_cpu_usage(
     use_current_cpu_total: bool,
 ) -> (f64, u64) {
     let new_proc_time = stat.utime + stat.stime;
-    let diff = (new_proc_time - prev_proc_time) as f64;
+    let diff = new_proc_time.saturating_sub(prev_proc_time) as f64;
 
     if cpu_usage == 0.0 {
         (0.0, new_proc_time)
@@ -289,3 +813,48 @@ fn get_cpu_usage(
         ((diff / cpu_usage) * 100_f64 * cpu_percentage, new_proc_time)
     }
 }
+
+// Sums the byte counters of the given TCP sockets, returning the totals and the received/sent
+// bytes per second since the previous sample.
+fn get_net_usage(tcp_inodes: &[INode], ctx: &SampleContext) -> (SocketBytes, (f64, f64)) {
+    let mut total = SocketBytes::default();
+    let mut delta = SocketBytes::default();
+
+    for inode in tcp_inodes {
+        if let Some(bytes) = ctx.socket_bytes.get(inode) {
+            total.received += bytes.received;
+            total.sent += bytes.sent;
+
+            // A socket first seen has no rate yet, rather than its whole count at once
+            if let Some(prev) = ctx.prev_socket_bytes.get(inode) {
+                delta.received += bytes.received.saturating_sub(prev.received);
+                delta.sent += bytes.sent.saturating_sub(prev.sent);
+            }
+        }
+    }
+
+    match ctx.socket_bytes_secs {
+        Some(secs) if secs > 0.0 => (
+            total,
+            (delta.received as f64 / secs, delta.sent as f64 / secs),
+        ),
+        _ => (total, (0.0, 0.0)),
+    }
+}
+
+// Per-second rates of each I/O counter since the previous tick.
+fn get_io_rates(io: &IoCounters, prev_io: &IoCounters, secs: f64) -> IoRates {
+    if secs <= 0.0 {
+        return IoRates::default();
+    }
+
+    let rate = |new: u64, prev: u64| new.saturating_sub(prev) as f64 / secs;
+    IoRates {
+        read_bytes: rate(io.read_bytes, prev_io.read_bytes),
+        write_bytes: rate(io.write_bytes, prev_io.write_bytes),
+        rchar: rate(io.rchar, prev_io.rchar),
+        wchar: rate(io.wchar, prev_io.wchar),
+        syscr: rate(io.syscr, prev_io.syscr),
+        syscw: rate(io.syscw, prev_io.syscw),
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{self, BufRead, Write};
+
+use crate::core::process::ProcData;
+
+// First line of a recording, naming its columns. One line follows per process and sample.
+const RECORD_HEADER: &str = "time,pid,ppid,user,priority,state,state_name,cpu_percent,\
+mem_percent,disk_read_bytes_per_sec,disk_write_bytes_per_sec,net_received_bytes_per_sec,\
+net_sent_bytes_per_sec,name,command";
+
+const RECORD_FIELDS: usize = 15;
+
+// Writes samples of the process list as CSV, which spreadsheets can open to plot how a
+// process' usage changes over time (e.g. after changing its priority).
+pub struct Recorder<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, "{}", RECORD_HEADER)?;
+        Ok(Recorder { out })
+    }
+
+    // Writes one line per process, all with the same time (seconds since the Unix epoch).
+    pub fn write_sample<'a>(
+        &mut self,
+        time: f64,
+        processes: impl IntoIterator<Item = &'a ProcData>,
+    ) -> io::Result<()> {
+        for p in processes {
+            writeln!(
+                self.out,
+                "{:.3},{},{},{},{},{},{},{:.4},{:.4},{:.1},{:.1},{:.1},{:.1},{},{}",
+                time,
+                p.pid,
+                p.parent_pid,
+                csv_field(p.user.as_deref().unwrap_or("")),
+                p.priority,
+                p.state.1,
+                csv_field(&p.state.0),
+                p.cpu_usage_percent,
+                p.mem_usage_percent,
+                p.disk_read_bytes_per_sec,
+                p.disk_write_bytes_per_sec,
+                p.net_received_bytes_per_sec,
+                p.net_sent_bytes_per_sec,
+                csv_field(&p.name),
+                csv_field(&p.command),
+            )?;
+        }
+        self.out.flush()
+    }
+}
+
+// Processes of one sample of a recording. Only the recorded fields are set.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedSample {
+    // Seconds since the Unix epoch.
+    pub time: f64,
+    pub processes: Vec<ProcData>,
+}
+
+// Reads back a recording written by Recorder, one entry per sample in the order recorded.
+pub fn read_recording(input: impl BufRead) -> io::Result<Vec<RecordedSample>> {
+    let mut samples: Vec<RecordedSample> = Vec::new();
+
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        if idx == 0 || line.is_empty() {
+            continue;
+        }
+        let (time, process) = parse_record_line(&line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: not a process record", idx + 1),
+            )
+        })?;
+        match samples.last_mut() {
+            Some(sample) if sample.time == time => sample.processes.push(process),
+            _ => samples.push(RecordedSample {
+                time,
+                processes: vec![process],
+            }),
+        }
+    }
+
+    Ok(samples)
+}
+
+pub fn parse_record_line(line: &str) -> Option<(f64, ProcData)> {
+    let fields = split_csv_line(line);
+    if fields.len() != RECORD_FIELDS {
+        return None;
+    }
+
+    let process = ProcData {
+        pid: fields[1].parse().ok()?,
+        parent_pid: fields[2].parse().ok()?,
+        user: Some(fields[3].clone()).filter(|user| !user.is_empty()),
+        priority: fields[4].parse().ok()?,
+        state: (fields[6].clone(), fields[5].chars().next()?),
+        cpu_usage_percent: fields[7].parse().ok()?,
+        mem_usage_percent: fields[8].parse().ok()?,
+        disk_read_bytes_per_sec: fields[9].parse().ok()?,
+        disk_write_bytes_per_sec: fields[10].parse().ok()?,
+        net_received_bytes_per_sec: fields[11].parse().ok()?,
+        net_sent_bytes_per_sec: fields[12].parse().ok()?,
+        name: fields[13].clone(),
+        command: fields[14].clone(),
+        ..Default::default()
+    };
+    Some((fields[0].parse().ok()?, process))
+}
+
+// Quotes a field holding commas or quotes, doubling its quotes. Line breaks (which arguments
+// may hold) become spaces, so every record stays on one line.
+fn csv_field(value: &str) -> String {
+    let value = value.replace(['\n', '\r'], " ");
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// Splits a CSV line into its fields, undoing csv_field's quoting.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: i32, command: &str) -> ProcData {
+        ProcData {
+            pid,
+            parent_pid: 1,
+            user: Some("alice".to_string()),
+            priority: 20,
+            state: ("Running".to_string(), 'R'),
+            cpu_usage_percent: 12.5,
+            mem_usage_percent: 1.25,
+            disk_read_bytes_per_sec: 1024.0,
+            name: "sh".to_string(),
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_back_recorded_samples() {
+        let mut out = Vec::new();
+        let mut recorder = Recorder::new(&mut out).unwrap();
+        recorder
+            .write_sample(10.0, &[process(2, "sh -c \"a, b\""), process(3, "sh\nx")])
+            .unwrap();
+        recorder.write_sample(11.0, &[process(2, "sh")]).unwrap();
+
+        let samples = read_recording(out.as_slice()).unwrap();
+
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].time, 10.0);
+        assert_eq!(samples[0].processes.len(), 2);
+        let first = &samples[0].processes[0];
+        assert_eq!(first.pid, 2);
+        assert_eq!(first.user.as_deref(), Some("alice"));
+        assert_eq!(first.state, ("Running".to_string(), 'R'));
+        assert_eq!(first.cpu_usage_percent, 12.5);
+        assert_eq!(first.disk_read_bytes_per_sec, 1024.0);
+        assert_eq!(first.command, "sh -c \"a, b\"");
+        assert_eq!(samples[0].processes[1].command, "sh x");
+        assert_eq!(samples[1].processes[0].pid, 2);
+    }
+
+    #[test]
+    fn rejects_lines_with_missing_fields() {
+        assert!(parse_record_line("10.000,2,1,alice").is_none());
+        assert!(read_recording(format!("{}\n1,2,3\n", RECORD_HEADER).as_bytes()).is_err());
+    }
+}
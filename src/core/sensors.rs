@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::source::DataSource;
+
+const HWMON_PATH: &str = "class/hwmon";
+const THERMAL_PATH: &str = "class/thermal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    // Reading in degrees Celsius.
+    Temperature,
+    // Reading in RPM.
+    Fan,
+}
+
+// A temperature or fan reading from hwmon or a thermal zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorData {
+    // Driver of the hwmon chip (e.g. coretemp), or "thermal" for thermal zones.
+    pub chip: String,
+
+    // Label of the sensor (e.g. Package id 0), or its file name (temp1, fan2) if it has none.
+    pub label: String,
+
+    pub kind: SensorKind,
+    pub value: f64,
+
+    // Value above which the hardware considers the sensor hot (tempN_max). None for fans.
+    pub high: Option<f64>,
+
+    // Value at which the hardware shuts down or throttles (tempN_crit, or the critical trip
+    // point of a thermal zone). For fans, the minimum speed (fanN_min) instead.
+    pub critical: Option<f64>,
+}
+
+impl SensorData {
+    // Whether the reading is past its critical threshold.
+    pub fn is_critical(&self) -> bool {
+        match (self.kind, self.critical) {
+            (SensorKind::Temperature, Some(crit)) => self.value >= crit,
+            (SensorKind::Fan, Some(min)) => self.value < min,
+            _ => false,
+        }
+    }
+
+    // Whether the reading is past its high threshold.
+    pub fn is_high(&self) -> bool {
+        self.high.is_some_and(|high| self.value >= high)
+    }
+}
+
+// Reads every hwmon temperature and fan input, then every thermal zone.
+pub fn read_sensors(source: &dyn DataSource) -> Vec<SensorData> {
+    let mut sensors = Vec::new();
+
+    for (_, chip_dir) in numbered_entries(&source.sys_path(HWMON_PATH), "hwmon") {
+        let chip = read_trimmed(&chip_dir.join("name")).unwrap_or_else(|| "hwmon".to_string());
+        for (n, _) in numbered_entries(&chip_dir, "temp") {
+            let file = |suffix: &str| chip_dir.join(format!("temp{}_{}", n, suffix));
+            let millidegrees = |suffix: &str| read_value(&file(suffix)).map(|v| v / 1000.0);
+            if let Some(value) = millidegrees("input") {
+                sensors.push(SensorData {
+                    chip: chip.clone(),
+                    label: read_trimmed(&file("label")).unwrap_or_else(|| format!("temp{}", n)),
+                    kind: SensorKind::Temperature,
+                    value,
+                    high: millidegrees("max"),
+                    critical: millidegrees("crit"),
+                });
+            }
+        }
+        for (n, _) in numbered_entries(&chip_dir, "fan") {
+            let file = |suffix: &str| chip_dir.join(format!("fan{}_{}", n, suffix));
+            if let Some(value) = read_value(&file("input")) {
+                sensors.push(SensorData {
+                    chip: chip.clone(),
+                    label: read_trimmed(&file("label")).unwrap_or_else(|| format!("fan{}", n)),
+                    kind: SensorKind::Fan,
+                    value,
+                    high: None,
+                    critical: read_value(&file("min")).filter(|min| *min > 0.0),
+                });
+            }
+        }
+    }
+
+    for (_, zone_dir) in numbered_entries(&source.sys_path(THERMAL_PATH), "thermal_zone") {
+        if let Some(value) = read_value(&zone_dir.join("temp")) {
+            sensors.push(SensorData {
+                chip: "thermal".to_string(),
+                label: read_trimmed(&zone_dir.join("type"))
+                    .unwrap_or_else(|| zone_dir.display().to_string()),
+                kind: SensorKind::Temperature,
+                value: value / 1000.0,
+                high: None,
+                critical: critical_trip_point(&zone_dir),
+            });
+        }
+    }
+
+    sensors
+}
+
+// Temperature of the trip point of type "critical" of a thermal zone, in degrees Celsius.
+fn critical_trip_point(zone_dir: &Path) -> Option<f64> {
+    (0..)
+        .map(|n| zone_dir.join(format!("trip_point_{}_type", n)))
+        .take_while(|path| path.exists())
+        .enumerate()
+        .find(|(_, path)| read_trimmed(path).as_deref() == Some("critical"))
+        .and_then(|(n, _)| read_value(&zone_dir.join(format!("trip_point_{}_temp", n))))
+        .map(|v| v / 1000.0)
+}
+
+// Entries of `dir` named `<prefix><number>` or `<prefix><number>_...`, once per number and in
+// numeric order, so hwmon10 comes after hwmon2.
+fn numbered_entries(dir: &Path, prefix: &str) -> Vec<(u32, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut numbered: Vec<(u32, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name();
+            let rest = name.to_str()?.strip_prefix(prefix)?;
+            let digits = rest.split('_').next()?;
+            Some((digits.parse().ok()?, entry.path()))
+        })
+        .collect();
+    numbered.sort_by_key(|(n, _)| *n);
+    numbered.dedup_by_key(|(n, _)| *n);
+    numbered
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let trimmed = contents.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn read_value(path: &Path) -> Option<f64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::source::RootedSource;
+
+    // A sysfs tree with sensors missing their name, label, thresholds or reading.
+    fn read_fixture() -> Vec<SensorData> {
+        let sys = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sensors");
+        read_sensors(&RootedSource::new("/nonexistent", sys))
+    }
+
+    fn labels(sensors: &[SensorData]) -> Vec<(&str, &str)> {
+        sensors
+            .iter()
+            .map(|s| (s.chip.as_str(), s.label.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_labels_in_numeric_order() {
+        let sensors = read_fixture();
+
+        // temp3 has a label but no reading, and thermal_zone2 has no temperature
+        let labels = labels(&sensors);
+        assert_eq!(
+            labels[..7],
+            [
+                ("nct6775", "SYSTIN"),
+                ("nct6775", "temp2"),
+                ("nct6775", "CPU fan"),
+                ("nct6775", "fan2"),
+                ("acpitz", "temp1"),
+                ("hwmon", "temp1"),
+                ("thermal", "acpitz"),
+            ]
+        );
+        assert_eq!(sensors.len(), 8);
+        // A thermal zone without a type is named after its directory
+        assert!(labels[7].1.ends_with("thermal_zone1"));
+    }
+
+    #[test]
+    fn reads_thresholds() {
+        let sensors = read_fixture();
+        let thresholds: Vec<(f64, Option<f64>, Option<f64>)> = sensors
+            .iter()
+            .map(|s| (s.value, s.high, s.critical))
+            .collect();
+
+        assert_eq!(
+            thresholds,
+            [
+                (38.0, Some(80.0), Some(95.0)),
+                (41.0, None, None),
+                (950.0, None, Some(300.0)),
+                // A zero minimum means the fan has none
+                (0.0, None, None),
+                (27.8, None, None),
+                (50.0, None, None),
+                // The critical trip point, not the passive one before it
+                (60.0, None, Some(110.0)),
+                (33.0, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_readings_past_their_thresholds() {
+        let sensor = |kind, value, high, critical| SensorData {
+            chip: "chip".to_string(),
+            label: "label".to_string(),
+            kind,
+            value,
+            high,
+            critical,
+        };
+
+        let hot = sensor(SensorKind::Temperature, 85.0, Some(80.0), Some(95.0));
+        assert!(hot.is_high() && !hot.is_critical());
+        let critical = sensor(SensorKind::Temperature, 95.0, Some(80.0), Some(95.0));
+        assert!(critical.is_critical());
+        let stalled = sensor(SensorKind::Fan, 100.0, None, Some(300.0));
+        assert!(stalled.is_critical() && !stalled.is_high());
+        let unknown = sensor(SensorKind::Temperature, 120.0, None, None);
+        assert!(!unknown.is_high() && !unknown.is_critical());
+    }
+}
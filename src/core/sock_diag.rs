@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+use crate::core::network::INode;
+
+// Netlink message type used to query sockets by address family (see linux/sock_diag.h).
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+
+// Extension asking the kernel to attach `struct tcp_info` to every socket (1 << (INET_DIAG_INFO - 1)).
+const INET_DIAG_INFO: u16 = 2;
+const INET_DIAG_INFO_EXT: u8 = 1 << (INET_DIAG_INFO - 1);
+
+// Request every TCP state.
+const ALL_TCP_STATES: u32 = 0xffff_ffff;
+
+const NLMSG_HDR_LEN: usize = 16;
+const INET_DIAG_REQ_V2_LEN: usize = 56;
+const INET_DIAG_MSG_LEN: usize = 72;
+const INET_DIAG_MSG_INODE_OFFSET: usize = 68;
+const RTATTR_HDR_LEN: usize = 4;
+
+// Offsets of the RFC4898 byte counters inside `struct tcp_info`.
+const TCPI_BYTES_ACKED_OFFSET: usize = 120;
+const TCPI_BYTES_RECEIVED_OFFSET: usize = 128;
+
+const RECV_BUFFER_LEN: usize = 32 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketBytes {
+    // Bytes received on the socket since it was opened.
+    pub received: u64,
+
+    // Bytes sent (and acknowledged by the peer) on the socket since it was opened.
+    pub sent: u64,
+}
+
+// Queries the kernel's sock_diag netlink interface for the byte counters of every TCP socket,
+// both IPv4 and IPv6, keyed by socket inode.
+pub fn get_tcp_socket_bytes() -> io::Result<HashMap<INode, SocketBytes>> {
+    let mut sockets = HashMap::new();
+
+    for family in [libc::AF_INET, libc::AF_INET6] {
+        dump_tcp_family(family as u8, &mut sockets)?;
+    }
+
+    Ok(sockets)
+}
+
+fn dump_tcp_family(family: u8, sockets: &mut HashMap<INode, SocketBytes>) -> io::Result<()> {
+    let fd = open_sock_diag()?;
+    send_request(&fd, family)?;
+
+    let mut buf = vec![0_u8; RECV_BUFFER_LEN];
+    loop {
+        let len = unsafe {
+            libc::recv(
+                fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The kernel always ends a dump with NLMSG_DONE, so running out of data before it means
+        // the dump was cut short.
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "sock_diag dump ended without NLMSG_DONE",
+            ));
+        }
+
+        if !parse_messages(&buf[..len as usize], sockets)? {
+            return Ok(());
+        }
+    }
+}
+
+fn open_sock_diag() -> io::Result<OwnedFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_SOCK_DIAG,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn send_request(fd: &OwnedFd, family: u8) -> io::Result<()> {
+    let msg_len = NLMSG_HDR_LEN + INET_DIAG_REQ_V2_LEN;
+    let mut msg = Vec::with_capacity(msg_len);
+
+    // struct nlmsghdr
+    msg.extend_from_slice(&(msg_len as u32).to_ne_bytes());
+    msg.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    msg.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16).to_ne_bytes());
+    msg.extend_from_slice(&1_u32.to_ne_bytes());
+    msg.extend_from_slice(&0_u32.to_ne_bytes());
+
+    // struct inet_diag_req_v2, with a zeroed inet_diag_sockid to match every socket.
+    msg.push(family);
+    msg.push(libc::IPPROTO_TCP as u8);
+    msg.push(INET_DIAG_INFO_EXT);
+    msg.push(0);
+    msg.extend_from_slice(&ALL_TCP_STATES.to_ne_bytes());
+    msg.resize(msg_len, 0);
+
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+
+    let sent = unsafe {
+        libc::sendto(
+            fd.as_raw_fd(),
+            msg.as_ptr() as *const libc::c_void,
+            msg.len(),
+            0,
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+// Parses one datagram worth of netlink messages. Returns false once the dump is complete.
+fn parse_messages(buf: &[u8], sockets: &mut HashMap<INode, SocketBytes>) -> io::Result<bool> {
+    let mut offset = 0;
+
+    while offset + NLMSG_HDR_LEN <= buf.len() {
+        let msg_len = read_u32(buf, offset) as usize;
+        let msg_type = read_u16(buf, offset + 4) as libc::c_int;
+        if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+            break;
+        }
+
+        match msg_type {
+            libc::NLMSG_DONE => return Ok(false),
+            libc::NLMSG_ERROR => {
+                let errno = buf
+                    .get(offset + NLMSG_HDR_LEN..offset + NLMSG_HDR_LEN + 4)
+                    .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .unwrap_or(0);
+                return Err(io::Error::from_raw_os_error(-errno));
+            }
+            _ => {
+                let payload = &buf[offset + NLMSG_HDR_LEN..offset + msg_len];
+                if let Some((inode, bytes)) = parse_inet_diag_msg(payload) {
+                    sockets.insert(inode, bytes);
+                }
+            }
+        }
+
+        offset += align(msg_len);
+    }
+
+    Ok(true)
+}
+
+fn parse_inet_diag_msg(payload: &[u8]) -> Option<(INode, SocketBytes)> {
+    if payload.len() < INET_DIAG_MSG_LEN {
+        return None;
+    }
+
+    let inode = read_u32(payload, INET_DIAG_MSG_INODE_OFFSET) as INode;
+    let mut offset = INET_DIAG_MSG_LEN;
+
+    while offset + RTATTR_HDR_LEN <= payload.len() {
+        let attr_len = read_u16(payload, offset) as usize;
+        let attr_type = read_u16(payload, offset + 2);
+        if attr_len < RTATTR_HDR_LEN || offset + attr_len > payload.len() {
+            break;
+        }
+
+        if attr_type == INET_DIAG_INFO {
+            let info = &payload[offset + RTATTR_HDR_LEN..offset + attr_len];
+            if info.len() >= TCPI_BYTES_RECEIVED_OFFSET + 8 {
+                let bytes = SocketBytes {
+                    received: read_u64(info, TCPI_BYTES_RECEIVED_OFFSET),
+                    sent: read_u64(info, TCPI_BYTES_ACKED_OFFSET),
+                };
+                return Some((inode, bytes));
+            }
+        }
+
+        offset += align(attr_len);
+    }
+
+    None
+}
+
+// Netlink messages and attributes are aligned to 4 bytes.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0_u8; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0_u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_ne_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Size of `struct tcp_info` up to and including tcpi_bytes_received.
+    const TCP_INFO_LEN: usize = TCPI_BYTES_RECEIVED_OFFSET + 8;
+
+    fn message(msg_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&((NLMSG_HDR_LEN + payload.len()) as u32).to_ne_bytes());
+        msg.extend_from_slice(&msg_type.to_ne_bytes());
+        msg.extend_from_slice(&0_u16.to_ne_bytes());
+        msg.extend_from_slice(&1_u32.to_ne_bytes());
+        msg.extend_from_slice(&0_u32.to_ne_bytes());
+        msg.extend_from_slice(payload);
+        msg.resize(align(msg.len()), 0);
+        msg
+    }
+
+    // An inet_diag_msg for `inode` followed by an INET_DIAG_INFO attribute holding `info_len`
+    // bytes of tcp_info.
+    fn inet_diag_msg(inode: u32, sent: u64, received: u64, info_len: usize) -> Vec<u8> {
+        let mut payload = vec![0_u8; INET_DIAG_MSG_LEN];
+        payload[INET_DIAG_MSG_INODE_OFFSET..INET_DIAG_MSG_INODE_OFFSET + 4]
+            .copy_from_slice(&inode.to_ne_bytes());
+
+        let mut info = vec![0_u8; TCP_INFO_LEN];
+        info[TCPI_BYTES_ACKED_OFFSET..TCPI_BYTES_ACKED_OFFSET + 8]
+            .copy_from_slice(&sent.to_ne_bytes());
+        info[TCPI_BYTES_RECEIVED_OFFSET..TCPI_BYTES_RECEIVED_OFFSET + 8]
+            .copy_from_slice(&received.to_ne_bytes());
+        info.truncate(info_len);
+
+        payload.extend_from_slice(&((RTATTR_HDR_LEN + info.len()) as u16).to_ne_bytes());
+        payload.extend_from_slice(&INET_DIAG_INFO.to_ne_bytes());
+        payload.extend_from_slice(&info);
+        message(SOCK_DIAG_BY_FAMILY, &payload)
+    }
+
+    #[test]
+    fn tcp_info_offsets_follow_the_kernel_layout() {
+        // inet_diag_msg: family, state, timer, retrans, a 48 byte inet_diag_sockid, then
+        // expires, rqueue, wqueue and uid before idiag_inode
+        assert_eq!(INET_DIAG_MSG_INODE_OFFSET, 4 + 48 + 4 * 4);
+        // tcp_info: 8 u8 fields, 24 u32 fields, then pacing_rate and max_pacing_rate
+        assert_eq!(TCPI_BYTES_ACKED_OFFSET, 8 + 24 * 4 + 2 * 8);
+        assert_eq!(TCPI_BYTES_RECEIVED_OFFSET, TCPI_BYTES_ACKED_OFFSET + 8);
+    }
+
+    #[test]
+    fn parses_socket_bytes_until_done() {
+        let mut buf = inet_diag_msg(4242, 100, 200, TCP_INFO_LEN);
+        buf.extend(inet_diag_msg(4343, 1, 2, TCP_INFO_LEN));
+        buf.extend(message(libc::NLMSG_DONE as u16, &0_i32.to_ne_bytes()));
+
+        let mut sockets = HashMap::new();
+        assert!(!parse_messages(&buf, &mut sockets).unwrap());
+        assert_eq!(
+            sockets,
+            HashMap::from([
+                (
+                    4242,
+                    SocketBytes {
+                        received: 200,
+                        sent: 100
+                    }
+                ),
+                (
+                    4343,
+                    SocketBytes {
+                        received: 2,
+                        sent: 1
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn asks_for_more_without_done() {
+        let mut sockets = HashMap::new();
+        assert!(parse_messages(&inet_diag_msg(4242, 1, 2, TCP_INFO_LEN), &mut sockets).unwrap());
+        assert_eq!(sockets.len(), 1);
+    }
+
+    #[test]
+    fn skips_truncated_messages() {
+        let mut sockets = HashMap::new();
+
+        // tcp_info from a kernel too old to have the byte counters
+        let short_info = inet_diag_msg(1, 1, 2, TCPI_BYTES_ACKED_OFFSET);
+        assert!(parse_messages(&short_info, &mut sockets).unwrap());
+
+        // inet_diag_msg cut off before the inode
+        let short_msg = message(SOCK_DIAG_BY_FAMILY, &[0_u8; INET_DIAG_MSG_LEN - 4]);
+        assert!(parse_messages(&short_msg, &mut sockets).unwrap());
+
+        // nlmsghdr claiming more bytes than were received
+        let cut = inet_diag_msg(2, 1, 2, TCP_INFO_LEN);
+        assert!(parse_messages(&cut[..cut.len() - 8], &mut sockets).unwrap());
+
+        assert!(sockets.is_empty());
+    }
+
+    #[test]
+    fn reports_netlink_errors() {
+        let buf = message(libc::NLMSG_ERROR as u16, &(-libc::EPERM).to_ne_bytes());
+
+        let error = parse_messages(&buf, &mut HashMap::new()).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EPERM));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::core::network::INode;
+use crate::core::sock_diag::{get_tcp_socket_bytes, SocketBytes};
+
+pub const DEFAULT_PROC_ROOT: &str = "/proc";
+pub const DEFAULT_SYS_ROOT: &str = "/sys";
+
+// Where the collectors read procfs and sysfs from. Paths given to it are relative to the root
+// of either filesystem, e.g. "net/dev" or "class/hwmon".
+pub trait DataSource: Send + Sync {
+    fn proc_root(&self) -> &Path;
+    fn sys_root(&self) -> &Path;
+
+    fn proc_path(&self, path: &str) -> PathBuf {
+        self.proc_root().join(path)
+    }
+
+    fn sys_path(&self, path: &str) -> PathBuf {
+        self.sys_root().join(path)
+    }
+
+    fn read_proc(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(self.proc_path(path))
+    }
+
+    fn read_sys(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(self.sys_path(path))
+    }
+
+    // Moves an absolute path read from the source itself, such as a mount point in
+    // /proc/self/mountinfo, under the sysfs root when it points into /sys.
+    fn rebase_sys_path(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(DEFAULT_SYS_ROOT) {
+            Ok(rest) => self.sys_root().join(rest),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    // Byte counters of every TCP socket, keyed by inode. They are not in any file, so a source
+    // that cannot ask the kernel has none.
+    fn tcp_socket_bytes(&self) -> io::Result<HashMap<INode, SocketBytes>> {
+        Ok(HashMap::new())
+    }
+}
+
+// Reads procfs and sysfs from directories: the live /proc and /sys by default, or e.g. a
+// container's /proc mounted elsewhere, or a copy of some of their files taken as a fixture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootedSource {
+    proc_root: PathBuf,
+    sys_root: PathBuf,
+}
+
+impl RootedSource {
+    pub fn new(proc_root: impl Into<PathBuf>, sys_root: impl Into<PathBuf>) -> Self {
+        RootedSource {
+            proc_root: proc_root.into(),
+            sys_root: sys_root.into(),
+        }
+    }
+}
+
+impl Default for RootedSource {
+    fn default() -> Self {
+        RootedSource::new(DEFAULT_PROC_ROOT, DEFAULT_SYS_ROOT)
+    }
+}
+
+impl DataSource for RootedSource {
+    fn proc_root(&self) -> &Path {
+        &self.proc_root
+    }
+
+    fn sys_root(&self) -> &Path {
+        &self.sys_root
+    }
+
+    // sock_diag answers for the sockets of the host rtop runs on, which only match the
+    // processes of the live /proc
+    fn tcp_socket_bytes(&self) -> io::Result<HashMap<INode, SocketBytes>> {
+        if self.proc_root == Path::new(DEFAULT_PROC_ROOT) {
+            get_tcp_socket_bytes()
+        } else {
+            Ok(HashMap::new())
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::core::cpu::KernelStat;
+use crate::core::process::ProcData;
+use crate::core::source::DataSource;
+
+const LOADAVG_PATH: &str = "loadavg";
+const UPTIME_PATH: &str = "uptime";
+
+// Load averages over the last 1, 5 and 15 minutes, from /proc/loadavg.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+// Number of processes in each state, counted like top does: uninterruptible and idle
+// processes count as sleeping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskCounts {
+    pub total: usize,
+    pub running: usize,
+    pub sleeping: usize,
+    pub stopped: usize,
+    pub zombie: usize,
+}
+
+impl TaskCounts {
+    pub fn count(processes: &[ProcData]) -> Self {
+        let mut counts = TaskCounts {
+            total: processes.len(),
+            ..Default::default()
+        };
+
+        for process in processes {
+            match process.state.1 {
+                'R' => counts.running += 1,
+                'S' | 'D' | 'I' => counts.sleeping += 1,
+                'T' | 't' => counts.stopped += 1,
+                'Z' => counts.zombie += 1,
+                _ => {}
+            }
+        }
+
+        counts
+    }
+}
+
+// System-wide summary shown at the top of the screen, like top's.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SystemSummary {
+    pub load: LoadAverage,
+
+    // Seconds since the system booted.
+    pub uptime_secs: u64,
+
+    // Time the system booted, in seconds since the Unix epoch.
+    pub boot_time: u64,
+
+    pub tasks: TaskCounts,
+
+    // Per-second rates of context switches, interrupts and forks since the last sample.
+    pub context_switches_per_sec: f64,
+    pub interrupts_per_sec: f64,
+    pub forks_per_sec: f64,
+}
+
+// Builds the summary from the processes and /proc/stat counters of this tick, and the
+// counters of the previous one. The load and uptime are left at zero when /proc/loadavg or
+// /proc/uptime cannot be read.
+pub fn read_system_summary(
+    source: &dyn DataSource,
+    processes: &[ProcData],
+    stat: &KernelStat,
+    prev_stat: &KernelStat,
+    elapsed_secs: Option<f64>,
+) -> SystemSummary {
+    let read = |path: &str| {
+        source
+            .read_proc(path)
+            .map_err(|err| log::warn!("cannot read {}: {}", path, err))
+            .ok()
+    };
+    let load = read(LOADAVG_PATH)
+        .and_then(|contents| parse_loadavg(&contents))
+        .unwrap_or_default();
+    let uptime_secs = read(UPTIME_PATH)
+        .and_then(|contents| parse_uptime(&contents))
+        .unwrap_or(0.0);
+
+    let rate = |new: u64, prev: u64| match elapsed_secs {
+        Some(secs) if secs > 0.0 => new.saturating_sub(prev) as f64 / secs,
+        _ => 0.0,
+    };
+
+    SystemSummary {
+        load,
+        uptime_secs: uptime_secs as u64,
+        boot_time: stat.boot_time,
+        tasks: TaskCounts::count(processes),
+        context_switches_per_sec: rate(stat.context_switches, prev_stat.context_switches),
+        interrupts_per_sec: rate(stat.interrupts, prev_stat.interrupts),
+        forks_per_sec: rate(stat.forks, prev_stat.forks),
+    }
+}
+
+pub fn parse_loadavg(contents: &str) -> Option<LoadAverage> {
+    let mut fields = contents.split_whitespace();
+    let mut next = || fields.next()?.parse::<f64>().ok();
+
+    Some(LoadAverage {
+        one: next()?,
+        five: next()?,
+        fifteen: next()?,
+    })
+}
+
+// Parses the first value of /proc/uptime, the seconds since boot.
+pub fn parse_uptime(contents: &str) -> Option<f64> {
+    contents.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_loadavg() {
+        let load = parse_loadavg("0.75 0.35 0.25 1/245 3312\n").unwrap();
+
+        assert_eq!(
+            load,
+            LoadAverage {
+                one: 0.75,
+                five: 0.35,
+                fifteen: 0.25,
+            }
+        );
+        assert_eq!(parse_loadavg("0.75 0.35\n"), None);
+    }
+
+    #[test]
+    fn parses_uptime() {
+        assert_eq!(parse_uptime("350735.47 234388.90\n"), Some(350735.47));
+        assert_eq!(parse_uptime(""), None);
+    }
+}
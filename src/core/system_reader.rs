@@ -1,53 +1,179 @@
 use std::collections::HashMap;
-use sysinfo::{self, System, SystemExt};
+use std::path::PathBuf;
+use std::time::Instant;
 
+use crate::core::cgroup::{find_cgroup2_mount, read_cgroup_data, CgroupCounters, CgroupData};
+use crate::core::cpu::{read_kernel_stat, CpuData, KernelStat};
+use crate::core::disk::{read_disk_data, DiskCounters, DiskData};
 use crate::core::error::RTopError;
-use crate::core::network::get_system_network_stats;
-use crate::core::process::{self, Pid, ProcData};
+use crate::core::filesystem::{read_filesystems, FilesystemData, PendingStatvfs};
+use crate::core::interrupts::{read_interrupt_data, InterruptData, IrqKind};
+use crate::core::memory::{read_meminfo, SystemMemory};
+use crate::core::network::{read_interface_data, InterfaceCounters, InterfaceData};
+use crate::core::pressure::{read_system_pressure, PressureData};
+use crate::core::process::{self, ProcData, ProcHistory};
+use crate::core::sensors::{read_sensors, SensorData};
+use crate::core::source::DataSource;
+use crate::core::summary::{read_system_summary, SystemSummary};
+use crate::core::thread::ThreadSelection;
+use crate::core::users::UserResolver;
+use crate::core::vmstat::{read_vmstat, VmActivity, VmCounters};
 
 pub struct SystemReader {
-    prev_idle: f64,
-    prev_non_idle: f64,
-    cpu_times: HashMap<Pid, u64>,
+    // Where procfs and sysfs are read from
+    source: Box<dyn DataSource>,
+    prev_kernel_stat: KernelStat,
+    prev_vmstat: Option<VmCounters>,
+    history: ProcHistory,
+    users: UserResolver,
+    cgroup_mount: Option<PathBuf>,
+    cgroup_counters: HashMap<String, CgroupCounters>,
+    interface_counters: HashMap<String, InterfaceCounters>,
+    disk_counters: HashMap<String, DiskCounters>,
+    pending_statvfs: PendingStatvfs,
+    irq_counters: HashMap<(IrqKind, String), Vec<u64>>,
+    last_sample: Option<Instant>,
     use_current_cpu_total: bool,
+    threads: ThreadSelection,
     pub total_memory_bytes: u64,
 }
 
+#[derive(Default)]
 pub struct SystemData {
     pub processes: Vec<ProcData>,
-    pub net_received_bytes: u64,
-    pub net_sent_bytes: u64,
+    pub interfaces: Vec<InterfaceData>,
+    pub disks: Vec<DiskData>,
+    pub filesystems: Vec<FilesystemData>,
+    pub sensors: Vec<SensorData>,
+    pub cgroups: Vec<CgroupData>,
+    pub cpu: CpuData,
+    pub memory: SystemMemory,
+    pub summary: SystemSummary,
+    pub pressure: PressureData,
+    pub vm: VmActivity,
+    pub interrupts: InterruptData,
 }
 
 impl SystemReader {
-    pub fn new(use_current_cpu_total: bool) -> Self {
-        let mut system = System::new_with_specifics(sysinfo::RefreshKind::new());
-        system.refresh_memory();
-
+    pub fn new(source: Box<dyn DataSource>, use_current_cpu_total: bool) -> Self {
         SystemReader {
-            prev_idle: 0.0,
-            prev_non_idle: 0.0,
-            cpu_times: HashMap::new(),
+            cgroup_mount: find_cgroup2_mount(source.as_ref()),
+            total_memory_bytes: read_meminfo(source.as_ref())
+                .map(|m| m.total_bytes)
+                .unwrap_or(0),
+            source,
+            prev_kernel_stat: KernelStat::default(),
+            prev_vmstat: None,
+            history: ProcHistory::default(),
+            users: UserResolver::new(),
+            cgroup_counters: HashMap::new(),
+            interface_counters: HashMap::new(),
+            disk_counters: HashMap::new(),
+            pending_statvfs: PendingStatvfs::new(),
+            irq_counters: HashMap::new(),
+            last_sample: None,
             use_current_cpu_total,
-            total_memory_bytes: system.total_memory(),
+            threads: ThreadSelection::default(),
         }
     }
 
+    pub fn set_use_current_cpu_total(&mut self, use_current_cpu_total: bool) {
+        self.use_current_cpu_total = use_current_cpu_total;
+    }
+
+    // Chooses the processes whose threads are read from the next tick on.
+    pub fn select_threads(&mut self, threads: ThreadSelection) {
+        self.threads = threads;
+    }
+
     pub fn read_process_data(&mut self) -> Result<SystemData, RTopError> {
+        let now = Instant::now();
+        let elapsed_secs = self
+            .last_sample
+            .map(|last| now.duration_since(last).as_secs_f64());
+        self.last_sample = Some(now);
+        self.users.refresh();
+        let source = self.source.as_ref();
+
+        // Without meminfo the memory panel is blank, and process memory percentages keep
+        // using the last total read
+        let memory = read_meminfo(source).unwrap_or_else(|err| {
+            log::warn!("cannot read meminfo: {}", err);
+            SystemMemory::default()
+        });
+        if memory.total_bytes > 0 {
+            self.total_memory_bytes = memory.total_bytes;
+        }
+
+        let kernel_stat = read_kernel_stat(source)?;
+        let cpu = CpuData::between(&kernel_stat, &self.prev_kernel_stat);
+        // CPUs online on the machine the source describes, not necessarily this one
+        let num_cpus = kernel_stat.cores.len();
+
         let processes = process::read_process_data(
-            &mut self.prev_idle,
-            &mut self.prev_non_idle,
-            &mut self.cpu_times,
-            self.use_current_cpu_total,
+            source,
+            (&kernel_stat, &self.prev_kernel_stat),
+            &mut self.history,
+            elapsed_secs,
+            (self.use_current_cpu_total, &self.threads),
             self.total_memory_bytes,
+            &self.users,
         )?;
 
-        let (net_received_bytes, net_sent_bytes) = get_system_network_stats();
+        let summary = read_system_summary(
+            source,
+            &processes,
+            &kernel_stat,
+            &self.prev_kernel_stat,
+            elapsed_secs,
+        );
+        self.prev_kernel_stat = kernel_stat;
+
+        let interfaces = read_interface_data(source, &mut self.interface_counters, elapsed_secs);
+        let disks = read_disk_data(source, &mut self.disk_counters, elapsed_secs);
+        let filesystems = read_filesystems(source, &mut self.pending_statvfs);
+        let interrupts = read_interrupt_data(source, &mut self.irq_counters, elapsed_secs);
+        let pressure = read_system_pressure(source);
+
+        // Without vmstat the VM view is blank, and rates start over once it can be read again
+        let vm = match read_vmstat(source) {
+            Ok(vmstat) => {
+                let vm = VmActivity::between(&vmstat, self.prev_vmstat.as_ref(), elapsed_secs);
+                self.prev_vmstat = Some(vmstat);
+                vm
+            }
+            Err(err) => {
+                log::warn!("cannot read vmstat: {}", err);
+                self.prev_vmstat = None;
+                VmActivity::default()
+            }
+        };
+        let sensors = read_sensors(source);
+
+        let cgroups = match &self.cgroup_mount {
+            Some(mount) => read_cgroup_data(
+                mount,
+                &processes,
+                &mut self.cgroup_counters,
+                elapsed_secs,
+                num_cpus,
+            ),
+            None => Vec::new(),
+        };
 
         Ok(SystemData {
             processes,
-            net_received_bytes,
-            net_sent_bytes,
+            interfaces,
+            disks,
+            filesystems,
+            sensors,
+            cgroups,
+            cpu,
+            memory,
+            summary,
+            pressure,
+            vm,
+            interrupts,
         })
     }
 }
@@ -0,0 +1,101 @@
+use procfs::process::Process;
+use std::collections::{HashMap, HashSet};
+use sysinfo::ProcessStatus;
+
+use crate::core::process::{get_cpu_usage, Pid, ProcKey, SampleContext};
+
+#[derive(Debug, Clone, Default)]
+pub struct ThreadData {
+    // Thread ID of the task.
+    pub tid: Pid,
+
+    // Pid of the process the thread belongs to.
+    pub pid: Pid,
+
+    // Name of the thread.
+    pub name: String,
+
+    // CPU usage as a percentage.
+    pub cpu_usage_percent: f64,
+
+    // Current state of the thread (e.g. running, asleep)
+    pub state: (String, char),
+
+    // Thread's priority
+    pub priority: i64,
+
+    // CPU the thread last ran on.
+    pub last_cpu: Option<i32>,
+}
+
+// Processes whose threads are read. Walking /proc/<pid>/task costs about as much as reading
+// the process itself, so threads are only read for the processes they are shown for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadSelection {
+    // Threads of these processes only; none by default.
+    Processes(HashSet<Pid>),
+    // Threads of every process.
+    All,
+}
+
+impl Default for ThreadSelection {
+    fn default() -> Self {
+        ThreadSelection::Processes(HashSet::new())
+    }
+}
+
+impl ThreadSelection {
+    pub fn includes(&self, pid: Pid) -> bool {
+        match self {
+            ThreadSelection::Processes(pids) => pids.contains(&pid),
+            ThreadSelection::All => true,
+        }
+    }
+}
+
+// Reads every task under /proc/<pid>/task, computing CPU usage from the CPU time each thread
+// had in `thread_cpu_times`. Returns the threads with their new CPU times, to be kept for the
+// next tick.
+pub(crate) fn read_thread_data(
+    proc: &Process,
+    ctx: &SampleContext,
+    thread_cpu_times: &HashMap<ProcKey, u64>,
+) -> (Vec<ThreadData>, Vec<(ProcKey, u64)>) {
+    let tasks = match proc.tasks() {
+        Ok(tasks) => tasks,
+        Err(_) => return (Vec::new(), Vec::new()),
+    };
+
+    tasks
+        .filter_map(|task| {
+            let task = task.ok()?;
+            let stat = task.stat().ok()?;
+
+            let key = ProcKey::new(task.tid, &stat);
+            // A thread missing from the previous tick, because it just started or its process
+            // was just selected, shows no usage until the next tick.
+            let prev_cpu_time = thread_cpu_times
+                .get(&key)
+                .copied()
+                .unwrap_or(stat.utime + stat.stime);
+            let (cpu_usage_percent, new_cpu_time) = get_cpu_usage(
+                &stat,
+                ctx.cpu_usage,
+                ctx.cpu_fraction,
+                prev_cpu_time,
+                ctx.use_current_cpu_total,
+            );
+
+            let thread = ThreadData {
+                tid: task.tid,
+                pid: task.pid,
+                name: stat.comm.clone(),
+                cpu_usage_percent,
+                state: (ProcessStatus::from(stat.state).to_string(), stat.state),
+                priority: stat.priority,
+                last_cpu: stat.processor,
+            };
+            Some((thread, (key, new_cpu_time)))
+        })
+        .unzip()
+}
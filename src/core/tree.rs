@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::process::{Pid, ProcData};
+
+// Usage of a process added up with that of all its descendants.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubtreeTotals {
+    // Number of processes in the subtree, including its root.
+    pub process_count: usize,
+    pub cpu_usage_percent: f64,
+    pub mem_usage_percent: f64,
+    pub total_disk_read_bytes: u64,
+    pub total_disk_write_bytes: u64,
+    pub disk_read_bytes_per_sec: f64,
+    pub disk_write_bytes_per_sec: f64,
+}
+
+impl SubtreeTotals {
+    fn of(process: &ProcData) -> Self {
+        SubtreeTotals {
+            process_count: 1,
+            cpu_usage_percent: process.cpu_usage_percent,
+            mem_usage_percent: process.mem_usage_percent,
+            total_disk_read_bytes: process.total_disk_read_bytes.unwrap_or(0),
+            total_disk_write_bytes: process.total_disk_write_bytes.unwrap_or(0),
+            disk_read_bytes_per_sec: process.disk_read_bytes_per_sec,
+            disk_write_bytes_per_sec: process.disk_write_bytes_per_sec,
+        }
+    }
+
+    fn add(&mut self, other: &SubtreeTotals) {
+        self.process_count += other.process_count;
+        self.cpu_usage_percent += other.cpu_usage_percent;
+        self.mem_usage_percent += other.mem_usage_percent;
+        self.total_disk_read_bytes += other.total_disk_read_bytes;
+        self.total_disk_write_bytes += other.total_disk_write_bytes;
+        self.disk_read_bytes_per_sec += other.disk_read_bytes_per_sec;
+        self.disk_write_bytes_per_sec += other.disk_write_bytes_per_sec;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcTreeNode {
+    pub pid: Pid,
+    // Distance from the root of the tree the process belongs to.
+    pub depth: usize,
+    // Children of the process, in the order they appear in the process list.
+    pub children: Vec<Pid>,
+    pub totals: SubtreeTotals,
+}
+
+// Processes arranged by parent pid.
+#[derive(Debug, Clone, Default)]
+pub struct ProcTree {
+    nodes: HashMap<Pid, ProcTreeNode>,
+    // Processes whose parent is not in the list (e.g. init and kthreadd).
+    roots: Vec<Pid>,
+}
+
+impl ProcTree {
+    // Builds the tree keeping the order of `processes` among siblings, so a sorted list
+    // produces a tree sorted within each level.
+    pub fn build<'a>(processes: impl IntoIterator<Item = &'a ProcData>) -> Self {
+        let processes: Vec<&ProcData> = processes.into_iter().collect();
+        let pids: HashSet<Pid> = processes.iter().map(|p| p.pid).collect();
+        let mut nodes: HashMap<Pid, ProcTreeNode> = processes
+            .iter()
+            .map(|p| {
+                let node = ProcTreeNode {
+                    pid: p.pid,
+                    totals: SubtreeTotals::of(p),
+                    ..Default::default()
+                };
+                (p.pid, node)
+            })
+            .collect();
+
+        let mut roots = Vec::new();
+        for process in processes.iter() {
+            let parent = process.parent_pid;
+            if parent != process.pid && pids.contains(&parent) {
+                if let Some(node) = nodes.get_mut(&parent) {
+                    node.children.push(process.pid);
+                }
+            } else {
+                roots.push(process.pid);
+            }
+        }
+
+        let mut tree = ProcTree { nodes, roots };
+        tree.compute_depths_and_totals();
+        tree
+    }
+
+    pub fn roots(&self) -> &[Pid] {
+        &self.roots
+    }
+
+    pub fn node(&self, pid: Pid) -> Option<&ProcTreeNode> {
+        self.nodes.get(&pid)
+    }
+
+    // Nodes in depth-first order, skipping the descendants of collapsed processes.
+    pub fn flatten(&self, collapsed: &HashSet<Pid>) -> Vec<&ProcTreeNode> {
+        let mut result = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<Pid> = self.roots.iter().rev().copied().collect();
+
+        while let Some(pid) = stack.pop() {
+            if let Some(node) = self.nodes.get(&pid) {
+                result.push(node);
+                if !collapsed.contains(&pid) {
+                    stack.extend(node.children.iter().rev());
+                }
+            }
+        }
+
+        result
+    }
+
+    fn compute_depths_and_totals(&mut self) {
+        // Depth-first order from the roots; a node is always visited after its parent.
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut visited = HashSet::with_capacity(self.nodes.len());
+        let mut stack: Vec<(Pid, usize)> = self.roots.iter().map(|pid| (*pid, 0)).collect();
+
+        while let Some((pid, depth)) = stack.pop() {
+            if !visited.insert(pid) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get_mut(&pid) {
+                node.depth = depth;
+                stack.extend(node.children.iter().map(|child| (*child, depth + 1)));
+                order.push(pid);
+            }
+        }
+
+        // Children are folded into their parents in reverse order, so every subtree is
+        // complete before it is added to its parent.
+        for pid in order.into_iter().rev() {
+            let children = match self.nodes.get(&pid) {
+                Some(node) => node.children.clone(),
+                None => continue,
+            };
+            let mut totals = self.nodes[&pid].totals;
+            for child in children {
+                if let Some(child) = self.nodes.get(&child) {
+                    totals.add(&child.totals);
+                }
+            }
+            if let Some(node) = self.nodes.get_mut(&pid) {
+                node.totals = totals;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: Pid, parent_pid: Pid, cpu_usage_percent: f64, read_bytes: u64) -> ProcData {
+        ProcData {
+            pid,
+            parent_pid,
+            cpu_usage_percent,
+            mem_usage_percent: 1.0,
+            total_disk_read_bytes: Some(read_bytes),
+            ..Default::default()
+        }
+    }
+
+    // init (1) -> sshd (10) -> bash (11) -> vim (12)
+    //          -> cron (20)
+    // kthreadd (2), whose parent is 0, and an orphan (30) whose parent exited
+    fn forest() -> Vec<ProcData> {
+        vec![
+            process(1, 0, 1.0, 100),
+            process(10, 1, 2.0, 10),
+            process(11, 10, 3.0, 1),
+            process(12, 11, 4.0, 0),
+            process(20, 1, 5.0, 1000),
+            process(2, 0, 0.5, 0),
+            process(30, 29, 6.0, 5),
+        ]
+    }
+
+    fn pids(nodes: &[&ProcTreeNode]) -> Vec<Pid> {
+        nodes.iter().map(|node| node.pid).collect()
+    }
+
+    #[test]
+    fn adds_up_subtrees() {
+        let tree = ProcTree::build(&forest());
+
+        let init = tree.node(1).unwrap().totals;
+        assert_eq!(init.process_count, 5);
+        assert_eq!(init.cpu_usage_percent, 15.0);
+        assert_eq!(init.mem_usage_percent, 5.0);
+        assert_eq!(init.total_disk_read_bytes, 1111);
+
+        let sshd = tree.node(10).unwrap().totals;
+        assert_eq!(sshd.process_count, 3);
+        assert_eq!(sshd.cpu_usage_percent, 9.0);
+        assert_eq!(sshd.total_disk_read_bytes, 11);
+
+        assert_eq!(
+            tree.node(12).unwrap().totals,
+            SubtreeTotals::of(&forest()[3])
+        );
+    }
+
+    #[test]
+    fn processes_without_a_listed_parent_are_roots() {
+        let tree = ProcTree::build(&forest());
+
+        assert_eq!(tree.roots(), [1, 2, 30]);
+        let orphan = tree.node(30).unwrap();
+        assert_eq!(orphan.depth, 0);
+        assert_eq!(orphan.totals.process_count, 1);
+        assert_eq!(tree.node(12).unwrap().depth, 3);
+    }
+
+    #[test]
+    fn reparented_processes_move_to_their_new_parent() {
+        // bash exits and vim is reparented to init
+        let mut processes = forest();
+        processes.retain(|p| p.pid != 11);
+        processes
+            .iter_mut()
+            .find(|p| p.pid == 12)
+            .unwrap()
+            .parent_pid = 1;
+
+        let tree = ProcTree::build(&processes);
+
+        assert_eq!(tree.node(1).unwrap().children, [10, 12, 20]);
+        assert_eq!(tree.node(10).unwrap().totals.process_count, 1);
+        assert_eq!(tree.node(12).unwrap().depth, 1);
+        assert_eq!(tree.node(1).unwrap().totals.process_count, 4);
+    }
+
+    #[test]
+    fn flattens_depth_first_skipping_collapsed_subtrees() {
+        let tree = ProcTree::build(&forest());
+
+        assert_eq!(
+            pids(&tree.flatten(&HashSet::new())),
+            [1, 10, 11, 12, 20, 2, 30]
+        );
+        assert_eq!(
+            pids(&tree.flatten(&HashSet::from([10]))),
+            [1, 10, 20, 2, 30]
+        );
+        assert_eq!(pids(&tree.flatten(&HashSet::from([1]))), [1, 2, 30]);
+
+        // Collapsing leaves their totals alone
+        assert_eq!(tree.node(10).unwrap().totals.process_count, 3);
+    }
+}
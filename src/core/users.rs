@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+use crate::core::process::{Gid, Uid};
+
+const PASSWD_PATH: &str = "/etc/passwd";
+const GROUP_PATH: &str = "/etc/group";
+
+// Resolves user and group IDs to names. The tables are cached and only re-read when
+// /etc/passwd or /etc/group change.
+#[derive(Debug, Default)]
+pub struct UserResolver {
+    users: HashMap<Uid, String>,
+    groups: HashMap<Gid, String>,
+    passwd_mtime: Option<SystemTime>,
+    group_mtime: Option<SystemTime>,
+}
+
+impl UserResolver {
+    pub fn new() -> Self {
+        let mut resolver = UserResolver::default();
+        resolver.refresh();
+        resolver
+    }
+
+    // Reloads the user and group tables if their files changed since they were last read.
+    pub fn refresh(&mut self) {
+        if let Some(users) = reload_if_changed(PASSWD_PATH, &mut self.passwd_mtime) {
+            self.users = users;
+        }
+        if let Some(groups) = reload_if_changed(GROUP_PATH, &mut self.group_mtime) {
+            self.groups = groups;
+        }
+    }
+
+    pub fn user_name(&self, uid: Uid) -> Option<&str> {
+        self.users.get(&uid).map(|name| name.as_str())
+    }
+
+    pub fn group_name(&self, gid: Gid) -> Option<&str> {
+        self.groups.get(&gid).map(|name| name.as_str())
+    }
+}
+
+// Parses files in the /etc/passwd or /etc/group format, where each line starts with
+// `name:password:id:`, into a map from ID to name.
+pub fn parse_id_names(contents: &str) -> HashMap<u32, String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse::<u32>().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+fn reload_if_changed(path: &str, mtime: &mut Option<SystemTime>) -> Option<HashMap<u32, String>> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if modified.is_some() && modified == *mtime {
+        return None;
+    }
+
+    let contents = fs::read_to_string(path).ok()?;
+    *mtime = modified;
+    Some(parse_id_names(&contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_id_names() {
+        let names = parse_id_names(
+            "# comment\n\
+             root:x:0:0:root:/root:/bin/bash\n\
+             alice:x:1000:1000::/home/alice:/bin/sh\n\
+             broken\n\
+             nobody:x:not-a-number:\n",
+        );
+
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&0], "root");
+        assert_eq!(names[&1000], "alice");
+    }
+}
@@ -0,0 +1,209 @@
+use std::io;
+
+use crate::core::source::DataSource;
+
+const VMSTAT_PATH: &str = "vmstat";
+
+// Swap traffic, in pages per second in and out together, above which swapping is considered a
+// storm: the system spends its time moving pages to and from swap rather than running.
+const SWAP_STORM_PAGES_PER_SEC: f64 = 1000.0;
+
+// Reclaim counters are split by who did the work (kswapd, direct reclaim, khugepaged, proactive
+// reclaim) and, on older kernels, also by zone (e.g. pgscan_kswapd_normal). The pgscan_anon
+// and pgscan_file counters of newer kernels repeat the same pages, so only these are added up.
+const RECLAIMERS: [&str; 4] = ["kswapd", "direct", "khugepaged", "proactive"];
+
+// Counts times direct reclaim was throttled, not pages, despite its name.
+const PGSCAN_DIRECT_THROTTLE: &str = "pgscan_direct_throttle";
+
+// Paging, swapping and OOM counters since boot, from /proc/vmstat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VmCounters {
+    // Page faults, and those that needed to read from disk.
+    pub page_faults: u64,
+    pub major_faults: u64,
+
+    // Pages read from and written to swap.
+    pub swap_in_pages: u64,
+    pub swap_out_pages: u64,
+
+    // Pages looked at and reclaimed by page reclaim.
+    pub pages_scanned: u64,
+    pub pages_stolen: u64,
+
+    // Processes killed by the OOM killer. Missing before Linux 4.13.
+    pub oom_kills: u64,
+}
+
+pub fn read_vmstat(source: &dyn DataSource) -> io::Result<VmCounters> {
+    Ok(parse_vmstat(&source.read_proc(VMSTAT_PATH)?))
+}
+
+pub fn parse_vmstat(contents: &str) -> VmCounters {
+    let mut counters = VmCounters::default();
+
+    for line in contents.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some((key, value)) => (key, value.trim().parse::<u64>().unwrap_or(0)),
+            None => continue,
+        };
+        match key {
+            "pgfault" => counters.page_faults = value,
+            "pgmajfault" => counters.major_faults = value,
+            "pswpin" => counters.swap_in_pages = value,
+            "pswpout" => counters.swap_out_pages = value,
+            "oom_kill" => counters.oom_kills = value,
+            _ => {
+                if is_reclaim_counter(key, "pgscan_") {
+                    counters.pages_scanned += value;
+                } else if is_reclaim_counter(key, "pgsteal_") {
+                    counters.pages_stolen += value;
+                }
+            }
+        }
+    }
+
+    counters
+}
+
+fn is_reclaim_counter(key: &str, prefix: &str) -> bool {
+    if key == PGSCAN_DIRECT_THROTTLE {
+        return false;
+    }
+    key.strip_prefix(prefix).is_some_and(|rest| {
+        RECLAIMERS
+            .iter()
+            .any(|r| rest == *r || rest.strip_prefix(r).is_some_and(|z| z.starts_with('_')))
+    })
+}
+
+// Paging activity since the previous sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VmActivity {
+    pub totals: VmCounters,
+
+    pub page_faults_per_sec: f64,
+    pub major_faults_per_sec: f64,
+    pub swap_in_pages_per_sec: f64,
+    pub swap_out_pages_per_sec: f64,
+    pub pages_scanned_per_sec: f64,
+    pub pages_stolen_per_sec: f64,
+
+    // Processes killed by the OOM killer since the previous sample.
+    pub new_oom_kills: u64,
+}
+
+impl VmActivity {
+    // Activity between two samples. With no previous sample (`prev` None) only the totals are
+    // known.
+    pub fn between(new: &VmCounters, prev: Option<&VmCounters>, elapsed_secs: Option<f64>) -> Self {
+        let mut activity = VmActivity {
+            totals: *new,
+            ..Default::default()
+        };
+        let (prev, secs) = match (prev, elapsed_secs) {
+            (Some(prev), Some(secs)) if secs > 0.0 => (prev, secs),
+            _ => return activity,
+        };
+
+        let rate = |new: u64, prev: u64| new.saturating_sub(prev) as f64 / secs;
+        activity.page_faults_per_sec = rate(new.page_faults, prev.page_faults);
+        activity.major_faults_per_sec = rate(new.major_faults, prev.major_faults);
+        activity.swap_in_pages_per_sec = rate(new.swap_in_pages, prev.swap_in_pages);
+        activity.swap_out_pages_per_sec = rate(new.swap_out_pages, prev.swap_out_pages);
+        activity.pages_scanned_per_sec = rate(new.pages_scanned, prev.pages_scanned);
+        activity.pages_stolen_per_sec = rate(new.pages_stolen, prev.pages_stolen);
+        activity.new_oom_kills = new.oom_kills.saturating_sub(prev.oom_kills);
+        activity
+    }
+
+    // Whether pages are moving to and from swap fast enough to stall the system.
+    pub fn is_swap_storm(&self) -> bool {
+        self.swap_in_pages_per_sec + self.swap_out_pages_per_sec >= SWAP_STORM_PAGES_PER_SEC
+    }
+
+    // Share of the scanned pages that could be reclaimed, in percent. Low values mean reclaim
+    // is struggling to find memory to free. None when nothing was scanned.
+    pub fn reclaim_efficiency_percent(&self) -> Option<f64> {
+        if self.pages_scanned_per_sec > 0.0 {
+            Some(self.pages_stolen_per_sec / self.pages_scanned_per_sec * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vmstat() {
+        let counters = parse_vmstat(
+            "pgfault 1000\n\
+             pgmajfault 10\n\
+             pswpin 3\n\
+             pswpout 7\n\
+             pgscan_kswapd 100\n\
+             pgscan_direct 50\n\
+             pgscan_direct_throttle 9\n\
+             pgscan_anon 120\n\
+             pgscan_file 30\n\
+             pgsteal_kswapd 80\n\
+             pgsteal_direct 20\n\
+             oom_kill 2\n",
+        );
+
+        assert_eq!(
+            counters,
+            VmCounters {
+                page_faults: 1000,
+                major_faults: 10,
+                swap_in_pages: 3,
+                swap_out_pages: 7,
+                pages_scanned: 150,
+                pages_stolen: 100,
+                oom_kills: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn adds_up_reclaim_counters_per_zone() {
+        let counters = parse_vmstat(
+            "pgscan_kswapd_dma 1\npgscan_kswapd_normal 2\npgscan_direct_movable 4\n\
+             pgsteal_kswapd_normal 1\n",
+        );
+
+        assert_eq!(counters.pages_scanned, 7);
+        assert_eq!(counters.pages_stolen, 1);
+    }
+
+    #[test]
+    fn computes_activity_between_samples() {
+        let prev = VmCounters {
+            swap_out_pages: 1000,
+            pages_scanned: 100,
+            pages_stolen: 50,
+            oom_kills: 1,
+            ..Default::default()
+        };
+        let new = VmCounters {
+            swap_out_pages: 3000,
+            pages_scanned: 500,
+            pages_stolen: 150,
+            oom_kills: 2,
+            ..Default::default()
+        };
+        let activity = VmActivity::between(&new, Some(&prev), Some(2.0));
+
+        assert_eq!(activity.swap_out_pages_per_sec, 1000.0);
+        assert!(activity.is_swap_storm());
+        assert_eq!(activity.reclaim_efficiency_percent(), Some(25.0));
+        assert_eq!(activity.new_oom_kills, 1);
+
+        let first = VmActivity::between(&new, None, None);
+        assert_eq!(first.totals, new);
+        assert_eq!(first.swap_out_pages_per_sec, 0.0);
+    }
+}
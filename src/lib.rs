@@ -1,2 +1,4 @@
+pub mod config;
 pub mod core;
+pub mod settings;
 pub mod ui;
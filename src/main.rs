@@ -1,34 +1,253 @@
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ErrorKind};
+
+use rtop::config::{default_config_path, SettingsSource};
+use rtop::core::process::{Pid, SortColumn};
+use rtop::core::source::{RootedSource, DEFAULT_PROC_ROOT, DEFAULT_SYS_ROOT};
 use rtop::core::system_reader::SystemReader;
+use rtop::settings::{Overrides, Settings, MAX_INTERVAL, MIN_INTERVAL};
+use rtop::ui::batch::{run_batch, run_record, run_replay};
 use rtop::ui::layout::start_ui;
 
-fn check_args(args: Vec<String>) -> Result<bool, &'static str> {
-    if args.len() == 2 {
-        if args[1].eq("-u") || args[1].eq("--current_usage") {
-            return Ok(true);
-        } else {
-            return Err("USAGE: rtop [FLAG]?\nFLAG: -u, --current_usage: Sets process CPU% usage to be based on the current system CPU% rather than total CPU usage.");
+fn command() -> Command<'static> {
+    let iterations = Arg::new("iterations")
+        .short('n')
+        .long("iterations")
+        .value_name("COUNT")
+        .value_parser(value_parser!(u64))
+        .help("Stop after this many samples instead of running until interrupted");
+
+    Command::new("rtop")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("System monitor for Linux")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .help("Config file [default: $XDG_CONFIG_HOME/rtop/config.toml]"),
+        )
+        .arg(
+            Arg::new("proc-root")
+                .long("proc-root")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .help("Read procfs from this directory [default: $RTOP_PROC_ROOT or /proc]"),
+        )
+        .arg(
+            Arg::new("sys-root")
+                .long("sys-root")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .help("Read sysfs from this directory [default: $RTOP_SYS_ROOT or /sys]"),
+        )
+        .arg(
+            Arg::new("interval")
+                .short('i')
+                .long("interval")
+                .value_name("SECONDS")
+                .value_parser(value_parser!(f64))
+                .global(true)
+                .help("Time between two refreshes, in seconds [default: 1]"),
+        )
+        .arg(
+            Arg::new("pid")
+                .short('p')
+                .long("pid")
+                .value_name("PID")
+                .value_parser(value_parser!(Pid))
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Only show these processes (comma-separated or repeated)"),
+        )
+        .arg(
+            Arg::new("user")
+                .short('U')
+                .long("user")
+                .value_name("USER")
+                .global(true)
+                .help("Only show the processes of this user name or uid"),
+        )
+        .arg(
+            Arg::new("sort")
+                .short('s')
+                .long("sort")
+                .value_name("COLUMN")
+                .value_parser(PossibleValuesParser::new(
+                    SortColumn::ALL.map(SortColumn::name),
+                ))
+                .global(true)
+                .help("Column to sort processes by [default: cpu]"),
+        )
+        .arg(
+            Arg::new("current-usage")
+                .short('u')
+                .long("current-usage")
+                .alias("current_usage")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no-current-usage")
+                .global(true)
+                .help(
+                    "Base process CPU% on the CPU time used during the interval rather than on \
+                     the total CPU time",
+                ),
+        )
+        .arg(
+            Arg::new("no-current-usage")
+                .long("no-current-usage")
+                .action(ArgAction::SetTrue)
+                .overrides_with("current-usage")
+                .global(true)
+                .help(
+                    "Base process CPU% on the total CPU time, even if the config file says not to",
+                ),
+        )
+        .subcommand(Command::new("tui").about("Interactive monitor (the default)"))
+        .subcommand(
+            Command::new("batch")
+                .about("Print the process table every interval, like top -b")
+                .arg(iterations.clone()),
+        )
+        .subcommand(
+            Command::new("record")
+                .about("Write a sample of the process table to a CSV file every interval")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(iterations),
+        )
+        .subcommand(
+            Command::new("replay")
+                .about("Print the samples of a recording as batch mode does")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("no-delay")
+                        .long("no-delay")
+                        .action(ArgAction::SetTrue)
+                        .help("Print every sample at once instead of as far apart as recorded"),
+                ),
+        )
+}
+
+fn overrides(matches: &ArgMatches) -> Overrides {
+    let mut overrides = Overrides::default();
+
+    if let Some(secs) = matches.get_one::<f64>("interval") {
+        match Duration::try_from_secs_f64(*secs) {
+            Ok(interval) if (MIN_INTERVAL..=MAX_INTERVAL).contains(&interval) => {
+                overrides.interval = Some(interval);
+            }
+            _ => command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "the interval must be between {} and {} seconds",
+                        MIN_INTERVAL.as_secs_f64(),
+                        MAX_INTERVAL.as_secs_f64()
+                    ),
+                )
+                .exit(),
         }
-    } else if args.len() > 2 {
-        return Err("Too many arguments");
+    }
+    if let Some(pids) = matches.get_many::<Pid>("pid") {
+        overrides.pids = pids.copied().collect();
+    }
+    overrides.user = matches.get_one::<String>("user").cloned();
+    overrides.sort_column = matches
+        .get_one::<String>("sort")
+        .and_then(|column| SortColumn::from_name(column));
+    let flag = |name: &str| matches.get_one::<bool>(name) == Some(&true);
+    if flag("current-usage") {
+        overrides.use_current_cpu_total = Some(true);
+    } else if flag("no-current-usage") {
+        overrides.use_current_cpu_total = Some(false);
     }
 
-    Ok(false)
+    overrides
 }
 
-fn main() -> Result<(), &'static str> {
-    let args: Vec<String> = env::args().collect();
-    match check_args(args) {
-        Ok(use_current_cpu_total) => {
-            let sys_data = SystemReader::new(use_current_cpu_total);
-            start_ui(sys_data).unwrap();
-        }
-        Err(msg) => {
-            println!("{}", msg);
+fn settings_source(matches: &ArgMatches) -> SettingsSource {
+    let config_path = matches.get_one::<PathBuf>("config");
+    SettingsSource {
+        config_required: config_path.is_some(),
+        config_path: config_path.cloned().or_else(default_config_path),
+        overrides: overrides(matches),
+    }
+}
+
+// Where procfs and sysfs are read from: the command line, then the environment, then the
+// live filesystems.
+fn data_source(matches: &ArgMatches) -> RootedSource {
+    let root = |arg: &str, var: &str, default: &str| {
+        matches
+            .get_one::<PathBuf>(arg)
+            .cloned()
+            .or_else(|| env::var_os(var).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(default))
+    };
+    RootedSource::new(
+        root("proc-root", "RTOP_PROC_ROOT", DEFAULT_PROC_ROOT),
+        root("sys-root", "RTOP_SYS_ROOT", DEFAULT_SYS_ROOT),
+    )
+}
+
+fn system_reader(matches: &ArgMatches, settings: &Settings) -> SystemReader {
+    SystemReader::new(
+        Box::new(data_source(matches)),
+        settings.use_current_cpu_total,
+    )
+}
+
+fn main() {
+    let matches = command().get_matches();
+    let source = settings_source(&matches);
+    let settings = match source.load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("rtop: {}", err.err_msg);
             std::process::exit(1);
         }
-    }
+    };
 
-    Ok(())
+    let result = match matches.subcommand() {
+        Some(("batch", args)) => run_batch(
+            system_reader(&matches, &settings),
+            &settings,
+            args.get_one::<u64>("iterations").copied(),
+        ),
+        Some(("record", args)) => run_record(
+            system_reader(&matches, &settings),
+            &settings,
+            args.get_one::<PathBuf>("file").expect("file is required"),
+            args.get_one::<u64>("iterations").copied(),
+        ),
+        Some(("replay", args)) => run_replay(
+            args.get_one::<PathBuf>("file").expect("file is required"),
+            &settings,
+            args.get_one::<bool>("no-delay") == Some(&true),
+        ),
+        _ => start_ui(system_reader(&matches, &settings), &settings, &source),
+    };
+
+    if let Err(err) = result {
+        eprintln!("rtop: {}", err.err_msg);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use crate::core::process::{Pid, SortColumn};
+use crate::ui::app::columns::ProcColumn;
+use crate::ui::keys::KeyMap;
+use crate::ui::theme::Theme;
+
+// Time between two samples unless told otherwise. The assignment asks for one second.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Shortest and longest refresh intervals, whether given on the command line or set with the
+// refresh keys.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+pub const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+// Options rtop runs with.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    // Time between two samples.
+    pub interval: Duration,
+
+    // Only these processes are shown, unless empty.
+    pub pids: Vec<Pid>,
+
+    // Only the processes of this user (name or uid) are shown, if set.
+    pub user: Option<String>,
+
+    // Column processes are sorted by at startup, and whether largest first (the column's own
+    // order unless set).
+    pub sort_column: SortColumn,
+    pub sort_descending: Option<bool>,
+
+    // Base process CPU% on the CPU time used during the interval rather than on all of it.
+    pub use_current_cpu_total: bool,
+
+    // Columns of the process table, in order.
+    pub columns: Vec<ProcColumn>,
+
+    // Colours of the interactive monitor.
+    pub theme: Theme,
+
+    // Keys of the interactive monitor.
+    pub keys: KeyMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            interval: DEFAULT_INTERVAL,
+            pids: Vec::new(),
+            user: None,
+            sort_column: SortColumn::Cpu,
+            sort_descending: None,
+            use_current_cpu_total: false,
+            columns: ProcColumn::DEFAULT.to_vec(),
+            theme: Theme::default(),
+            keys: KeyMap::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn sort_descending(&self) -> bool {
+        self.sort_descending
+            .unwrap_or_else(|| self.sort_column.descending_by_default())
+    }
+}
+
+// Options given on the command line. They take precedence over the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub interval: Option<Duration>,
+    pub pids: Vec<Pid>,
+    pub user: Option<String>,
+    pub sort_column: Option<SortColumn>,
+    // Set by --current-usage or --no-current-usage
+    pub use_current_cpu_total: Option<bool>,
+}
+
+impl Overrides {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(interval) = self.interval {
+            settings.interval = interval;
+        }
+        if !self.pids.is_empty() {
+            settings.pids = self.pids.clone();
+        }
+        if let Some(user) = &self.user {
+            settings.user = Some(user.clone());
+        }
+        if let Some(column) = self.sort_column {
+            settings.sort_column = column;
+            settings.sort_descending = None;
+        }
+        if let Some(use_current_cpu_total) = self.use_current_cpu_total {
+            settings.use_current_cpu_total = use_current_cpu_total;
+        }
+    }
+}
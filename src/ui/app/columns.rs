@@ -0,0 +1,527 @@
+use crate::core::process::{Credentials, ProcData, SortColumn};
+use crate::core::thread::ThreadData;
+use crate::core::tree::ProcTreeNode;
+
+// Whether I/O columns show per-second rates or totals since the process started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoMode {
+    Rate,
+    Total,
+}
+
+// Columns of the process table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcColumn {
+    Pid,
+    ParentPid,
+    Priority,
+    StartTime,
+    RunTime,
+    Mem,
+    Uss,
+    Pss,
+    SharedMem,
+    AnonMem,
+    Swap,
+    Cpu,
+    SubtreeCpu,
+    SubtreeMem,
+    SubtreeDiskRead,
+    SubtreeDiskWrite,
+    DiskRead,
+    DiskWrite,
+    ReadChars,
+    WriteChars,
+    ReadSyscalls,
+    WriteSyscalls,
+    NetReceived,
+    NetSent,
+    NetReceivedRate,
+    NetSentRate,
+    State,
+    LastCpu,
+    Uid,
+    User,
+    EffectiveUser,
+    Group,
+    Uids,
+    Gids,
+    NsPid,
+    Container,
+    Name,
+    TcpPorts,
+    UdpPorts,
+    Command,
+}
+
+impl ProcColumn {
+    pub const ALL: [ProcColumn; 36] = [
+        ProcColumn::Pid,
+        ProcColumn::ParentPid,
+        ProcColumn::Priority,
+        ProcColumn::StartTime,
+        ProcColumn::RunTime,
+        ProcColumn::Mem,
+        ProcColumn::Uss,
+        ProcColumn::Pss,
+        ProcColumn::SharedMem,
+        ProcColumn::AnonMem,
+        ProcColumn::Swap,
+        ProcColumn::Cpu,
+        ProcColumn::DiskRead,
+        ProcColumn::DiskWrite,
+        ProcColumn::ReadChars,
+        ProcColumn::WriteChars,
+        ProcColumn::ReadSyscalls,
+        ProcColumn::WriteSyscalls,
+        ProcColumn::NetReceived,
+        ProcColumn::NetSent,
+        ProcColumn::NetReceivedRate,
+        ProcColumn::NetSentRate,
+        ProcColumn::State,
+        ProcColumn::LastCpu,
+        ProcColumn::Uid,
+        ProcColumn::User,
+        ProcColumn::EffectiveUser,
+        ProcColumn::Group,
+        ProcColumn::Uids,
+        ProcColumn::Gids,
+        ProcColumn::NsPid,
+        ProcColumn::Container,
+        ProcColumn::Name,
+        ProcColumn::TcpPorts,
+        ProcColumn::UdpPorts,
+        ProcColumn::Command,
+    ];
+
+    // Columns shown unless the config file sets others: the original table's, with the user
+    // name. They leave room for the command in a 138 columns wide terminal.
+    pub const DEFAULT: [ProcColumn; 14] = [
+        ProcColumn::Pid,
+        ProcColumn::ParentPid,
+        ProcColumn::User,
+        ProcColumn::Priority,
+        ProcColumn::Mem,
+        ProcColumn::Cpu,
+        ProcColumn::DiskRead,
+        ProcColumn::DiskWrite,
+        ProcColumn::State,
+        ProcColumn::Uid,
+        ProcColumn::Name,
+        ProcColumn::TcpPorts,
+        ProcColumn::UdpPorts,
+        ProcColumn::Command,
+    ];
+
+    // Totals of every process in a subtree, shown in the tree view.
+    pub const SUBTREE: [ProcColumn; 4] = [
+        ProcColumn::SubtreeCpu,
+        ProcColumn::SubtreeMem,
+        ProcColumn::SubtreeDiskRead,
+        ProcColumn::SubtreeDiskWrite,
+    ];
+
+    // Name of the column in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProcColumn::Pid => "pid",
+            ProcColumn::ParentPid => "ppid",
+            ProcColumn::Priority => "priority",
+            ProcColumn::StartTime => "start",
+            ProcColumn::RunTime => "time",
+            ProcColumn::Mem => "mem",
+            ProcColumn::Uss => "uss",
+            ProcColumn::Pss => "pss",
+            ProcColumn::SharedMem => "shared",
+            ProcColumn::AnonMem => "anon",
+            ProcColumn::Swap => "swap",
+            ProcColumn::Cpu => "cpu",
+            ProcColumn::SubtreeCpu => "subtree-cpu",
+            ProcColumn::SubtreeMem => "subtree-mem",
+            ProcColumn::SubtreeDiskRead => "subtree-read",
+            ProcColumn::SubtreeDiskWrite => "subtree-write",
+            ProcColumn::DiskRead => "read",
+            ProcColumn::DiskWrite => "write",
+            ProcColumn::ReadChars => "read-chars",
+            ProcColumn::WriteChars => "write-chars",
+            ProcColumn::ReadSyscalls => "read-syscalls",
+            ProcColumn::WriteSyscalls => "write-syscalls",
+            ProcColumn::NetReceived => "net-rx-total",
+            ProcColumn::NetSent => "net-tx-total",
+            ProcColumn::NetReceivedRate => "net-rx",
+            ProcColumn::NetSentRate => "net-tx",
+            ProcColumn::State => "state",
+            ProcColumn::LastCpu => "last-cpu",
+            ProcColumn::Uid => "uid",
+            ProcColumn::User => "user",
+            ProcColumn::EffectiveUser => "euser",
+            ProcColumn::Group => "group",
+            ProcColumn::Uids => "uids",
+            ProcColumn::Gids => "gids",
+            ProcColumn::NsPid => "nspid",
+            ProcColumn::Container => "container",
+            ProcColumn::Name => "name",
+            ProcColumn::TcpPorts => "tcp-ports",
+            ProcColumn::UdpPorts => "udp-ports",
+            ProcColumn::Command => "command",
+        }
+    }
+
+    // Only the columns of `ALL` can be chosen; the subtree totals come with the tree view.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ProcColumn::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    pub fn header(self, io_mode: IoMode) -> &'static str {
+        use IoMode::{Rate, Total};
+
+        match (self, io_mode) {
+            (ProcColumn::Pid, _) => "PID",
+            (ProcColumn::ParentPid, _) => "PAID",
+            (ProcColumn::Priority, _) => "PRI",
+            (ProcColumn::StartTime, _) => "START",
+            (ProcColumn::RunTime, _) => "ELAPSED",
+            (ProcColumn::Mem, _) => "MEM%",
+            (ProcColumn::Uss, _) => "USS(KB)",
+            (ProcColumn::Pss, _) => "PSS(KB)",
+            (ProcColumn::SharedMem, _) => "SHR(KB)",
+            (ProcColumn::AnonMem, _) => "ANON(KB)",
+            (ProcColumn::Swap, _) => "SWAP(KB)",
+            (ProcColumn::Cpu, _) => "CPU%",
+            (ProcColumn::SubtreeCpu, _) => "ΣCPU%",
+            (ProcColumn::SubtreeMem, _) => "ΣMEM%",
+            (ProcColumn::SubtreeDiskRead, Total) => "ΣREAD(KB)",
+            (ProcColumn::SubtreeDiskRead, Rate) => "ΣREAD(KB/s)",
+            (ProcColumn::SubtreeDiskWrite, Total) => "ΣWRITE(KB)",
+            (ProcColumn::SubtreeDiskWrite, Rate) => "ΣWRITE(KB/s)",
+            (ProcColumn::DiskRead, Total) => "READ(KB)",
+            (ProcColumn::DiskRead, Rate) => "READ(KB/s)",
+            (ProcColumn::DiskWrite, Total) => "WRITE(KB)",
+            (ProcColumn::DiskWrite, Rate) => "WRITE(KB/s)",
+            (ProcColumn::ReadChars, Total) => "RCHAR(KB)",
+            (ProcColumn::ReadChars, Rate) => "RCHAR(KB/s)",
+            (ProcColumn::WriteChars, Total) => "WCHAR(KB)",
+            (ProcColumn::WriteChars, Rate) => "WCHAR(KB/s)",
+            (ProcColumn::ReadSyscalls, Total) => "SYSCR",
+            (ProcColumn::ReadSyscalls, Rate) => "SYSCR/s",
+            (ProcColumn::WriteSyscalls, Total) => "SYSCW",
+            (ProcColumn::WriteSyscalls, Rate) => "SYSCW/s",
+            (ProcColumn::NetReceived, _) => "NET_RX(KB)",
+            (ProcColumn::NetSent, _) => "NET_TX(KB)",
+            (ProcColumn::NetReceivedRate, _) => "RX(KB/s)",
+            (ProcColumn::NetSentRate, _) => "TX(KB/s)",
+            (ProcColumn::State, _) => "STATE",
+            (ProcColumn::LastCpu, _) => "LAST_CPU",
+            (ProcColumn::Uid, _) => "UID",
+            (ProcColumn::User, _) => "USER",
+            (ProcColumn::EffectiveUser, _) => "EUSER",
+            (ProcColumn::Group, _) => "GROUP",
+            (ProcColumn::Uids, _) => "UIDS(R/E/S/FS)",
+            (ProcColumn::Gids, _) => "GIDS(R/E/S/FS)",
+            (ProcColumn::NsPid, _) => "NSPID",
+            (ProcColumn::Container, _) => "CONTAINER",
+            (ProcColumn::Name, _) => "NAME",
+            (ProcColumn::TcpPorts, _) => "TCP_PORTS",
+            (ProcColumn::UdpPorts, _) => "UDP_PORTS",
+            (ProcColumn::Command, _) => "COMMAND",
+        }
+    }
+
+    // Minimum width of the column.
+    pub fn width(self) -> u16 {
+        match self {
+            ProcColumn::Pid | ProcColumn::ParentPid | ProcColumn::Priority => 5,
+            ProcColumn::Mem | ProcColumn::Cpu | ProcColumn::Uid => 5,
+            ProcColumn::SubtreeCpu | ProcColumn::SubtreeMem => 7,
+            ProcColumn::SubtreeDiskRead | ProcColumn::SubtreeDiskWrite => 12,
+            ProcColumn::Uss | ProcColumn::Pss | ProcColumn::SharedMem => 8,
+            ProcColumn::AnonMem | ProcColumn::Swap | ProcColumn::LastCpu => 8,
+            ProcColumn::NetReceivedRate | ProcColumn::NetSentRate => 8,
+            ProcColumn::ReadSyscalls | ProcColumn::WriteSyscalls => 8,
+            ProcColumn::DiskRead | ProcColumn::DiskWrite => 11,
+            ProcColumn::ReadChars | ProcColumn::WriteChars => 11,
+            ProcColumn::NetReceived | ProcColumn::NetSent | ProcColumn::State => 10,
+            ProcColumn::StartTime => 6,
+            ProcColumn::RunTime => 11,
+            ProcColumn::User | ProcColumn::EffectiveUser | ProcColumn::Group => 10,
+            ProcColumn::Uids | ProcColumn::Gids => 23,
+            ProcColumn::NsPid => 6,
+            ProcColumn::Container => 19,
+            ProcColumn::Name => 20,
+            ProcColumn::TcpPorts | ProcColumn::UdpPorts => 11,
+            ProcColumn::Command => 1000,
+        }
+    }
+
+    // Sort key matching what the column currently displays, if it can be sorted.
+    pub fn sort_column(self, io_mode: IoMode) -> Option<SortColumn> {
+        match (self, io_mode) {
+            (ProcColumn::Pid, _) => Some(SortColumn::Pid),
+            (ProcColumn::RunTime, _) => Some(SortColumn::RunTime),
+            (ProcColumn::Mem, _) => Some(SortColumn::Mem),
+            (ProcColumn::Uss, _) => Some(SortColumn::Uss),
+            (ProcColumn::Pss, _) => Some(SortColumn::Pss),
+            (ProcColumn::SharedMem, _) => Some(SortColumn::SharedMem),
+            (ProcColumn::AnonMem, _) => Some(SortColumn::AnonMem),
+            (ProcColumn::Swap, _) => Some(SortColumn::Swap),
+            (ProcColumn::Cpu, _) => Some(SortColumn::Cpu),
+            (ProcColumn::DiskRead, IoMode::Total) => Some(SortColumn::DiskRead),
+            (ProcColumn::DiskRead, IoMode::Rate) => Some(SortColumn::DiskReadRate),
+            (ProcColumn::DiskWrite, IoMode::Total) => Some(SortColumn::DiskWrite),
+            (ProcColumn::DiskWrite, IoMode::Rate) => Some(SortColumn::DiskWriteRate),
+            (ProcColumn::NetReceivedRate, _) => Some(SortColumn::NetReceived),
+            (ProcColumn::NetSentRate, _) => Some(SortColumn::NetSent),
+            (ProcColumn::User, _) => Some(SortColumn::User),
+            (ProcColumn::Container, _) => Some(SortColumn::Container),
+            (ProcColumn::Name, _) => Some(SortColumn::Name),
+            _ => None,
+        }
+    }
+
+    pub fn process_cell(self, process: &ProcData, expanded: bool, io_mode: IoMode) -> String {
+        use IoMode::{Rate, Total};
+
+        match (self, io_mode) {
+            (ProcColumn::Pid, _) => process.pid.to_string(),
+            (ProcColumn::ParentPid, _) => process.parent_pid.to_string(),
+            (ProcColumn::Priority, _) => process.priority.to_string(),
+            (ProcColumn::StartTime, _) => {
+                start_time_string(process.start_time, process.run_time_secs)
+            }
+            (ProcColumn::RunTime, _) => duration_string(process.run_time_secs),
+            (ProcColumn::Mem, _) => format!("{:.4}", process.mem_usage_percent.to_string()),
+            (ProcColumn::Uss, _) => kb_string(process.uss_bytes),
+            (ProcColumn::Pss, _) => kb_string(process.pss_bytes),
+            (ProcColumn::SharedMem, _) => kb_string(process.shared_mem_bytes),
+            (ProcColumn::AnonMem, _) => kb_string(process.anon_mem_bytes),
+            (ProcColumn::Swap, _) => kb_string(process.swap_bytes),
+            (ProcColumn::Cpu, _) => format!("{:.4}", process.cpu_usage_percent.to_string()),
+            (ProcColumn::SubtreeCpu, _) | (ProcColumn::SubtreeMem, _) => "-".to_string(),
+            (ProcColumn::SubtreeDiskRead, _) | (ProcColumn::SubtreeDiskWrite, _) => "-".to_string(),
+            (ProcColumn::DiskRead, Total) => kb_string(process.total_disk_read_bytes),
+            (ProcColumn::DiskRead, Rate) => kb_rate_string(process.disk_read_bytes_per_sec),
+            (ProcColumn::DiskWrite, Total) => kb_string(process.total_disk_write_bytes),
+            (ProcColumn::DiskWrite, Rate) => kb_rate_string(process.disk_write_bytes_per_sec),
+            (ProcColumn::ReadChars, Total) => kb_string(process.total_read_chars),
+            (ProcColumn::ReadChars, Rate) => kb_rate_string(process.read_chars_per_sec),
+            (ProcColumn::WriteChars, Total) => kb_string(process.total_write_chars),
+            (ProcColumn::WriteChars, Rate) => kb_rate_string(process.write_chars_per_sec),
+            (ProcColumn::ReadSyscalls, Total) => count_string(process.total_read_syscalls),
+            (ProcColumn::ReadSyscalls, Rate) => format!("{:.1}", process.read_syscalls_per_sec),
+            (ProcColumn::WriteSyscalls, Total) => count_string(process.total_write_syscalls),
+            (ProcColumn::WriteSyscalls, Rate) => {
+                format!("{:.1}", process.write_syscalls_per_sec)
+            }
+            (ProcColumn::NetReceived, _) => (process.total_net_received_bytes / 1000).to_string(),
+            (ProcColumn::NetSent, _) => (process.total_net_sent_bytes / 1000).to_string(),
+            (ProcColumn::NetReceivedRate, _) => kb_rate_string(process.net_received_bytes_per_sec),
+            (ProcColumn::NetSentRate, _) => kb_rate_string(process.net_sent_bytes_per_sec),
+            (ProcColumn::State, _) => process.state.0.clone(),
+            (ProcColumn::LastCpu, _) => last_cpu_string(process.last_cpu),
+            (ProcColumn::Uid, _) => count_string(process.uid.map(u64::from)),
+            (ProcColumn::User, _) => name_string(&process.user, process.uids.map(|c| c.real)),
+            (ProcColumn::EffectiveUser, _) => {
+                let name = name_string(&process.effective_user, process.uids.map(|c| c.effective));
+                match process.uids {
+                    Some(uids) if uids.is_elevated() => format!("{}*", name),
+                    _ => name,
+                }
+            }
+            (ProcColumn::Group, _) => name_string(&process.group, process.gids.map(|c| c.real)),
+            (ProcColumn::Uids, _) => credentials_string(process.uids),
+            (ProcColumn::Gids, _) => credentials_string(process.gids),
+            (ProcColumn::NsPid, _) => match process.ns_pids.last() {
+                Some(pid) => pid.to_string(),
+                None => "-".to_string(),
+            },
+            (ProcColumn::Container, _) => match &process.container {
+                Some(container) => container.label(),
+                None => "-".to_string(),
+            },
+            (ProcColumn::Name, _) => match (process.num_threads > 1, expanded) {
+                (true, true) => format!("- {}", process.name),
+                (true, false) => format!("+ {}", process.name),
+                _ => process.name.to_string(),
+            },
+            (ProcColumn::TcpPorts, _) => ports_string(&process.tcp_ports),
+            (ProcColumn::UdpPorts, _) => ports_string(&process.udp_ports),
+            (ProcColumn::Command, _) => process.command.to_string(),
+        }
+    }
+
+    pub fn tree_cell(
+        self,
+        process: &ProcData,
+        node: &ProcTreeNode,
+        collapsed: bool,
+        io_mode: IoMode,
+    ) -> String {
+        let totals = &node.totals;
+        match (self, io_mode) {
+            (ProcColumn::Name, _) => {
+                let marker = match (node.children.is_empty(), collapsed) {
+                    (true, _) => "  ",
+                    (false, true) => "+ ",
+                    (false, false) => "- ",
+                };
+                format!("{}{}{}", "  ".repeat(node.depth), marker, process.name)
+            }
+            (ProcColumn::SubtreeCpu, _) => format!("{:.2}", totals.cpu_usage_percent),
+            (ProcColumn::SubtreeMem, _) => format!("{:.2}", totals.mem_usage_percent),
+            (ProcColumn::SubtreeDiskRead, IoMode::Total) => {
+                kb_string(Some(totals.total_disk_read_bytes))
+            }
+            (ProcColumn::SubtreeDiskRead, IoMode::Rate) => {
+                kb_rate_string(totals.disk_read_bytes_per_sec)
+            }
+            (ProcColumn::SubtreeDiskWrite, IoMode::Total) => {
+                kb_string(Some(totals.total_disk_write_bytes))
+            }
+            (ProcColumn::SubtreeDiskWrite, IoMode::Rate) => {
+                kb_rate_string(totals.disk_write_bytes_per_sec)
+            }
+            _ => self.process_cell(process, false, io_mode),
+        }
+    }
+
+    pub fn thread_cell(self, thread: &ThreadData) -> String {
+        match self {
+            ProcColumn::Pid => thread.tid.to_string(),
+            ProcColumn::ParentPid => thread.pid.to_string(),
+            ProcColumn::Priority => thread.priority.to_string(),
+            ProcColumn::Cpu => format!("{:.4}", thread.cpu_usage_percent.to_string()),
+            ProcColumn::State => thread.state.0.clone(),
+            ProcColumn::LastCpu => last_cpu_string(thread.last_cpu),
+            ProcColumn::Name => format!("  {}", thread.name),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+pub(crate) fn kb_string(bytes: Option<u64>) -> String {
+    bytes
+        .map(|bytes| (bytes / 1000).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+pub(crate) fn kb_rate_string(bytes_per_sec: f64) -> String {
+    format!("{:.1}", bytes_per_sec / 1000.0)
+}
+
+fn count_string(count: Option<u64>) -> String {
+    count
+        .map(|count| count.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+// Resolved name, falling back to the numeric ID when it has no name.
+fn name_string(name: &Option<String>, id: Option<u32>) -> String {
+    match (name, id) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(id)) => id.to_string(),
+        (None, None) => "-".to_string(),
+    }
+}
+
+// IDs formatted as real/effective/saved/filesystem, flagged with a `*` when they differ.
+fn credentials_string(ids: Option<Credentials<u32>>) -> String {
+    match ids {
+        Some(ids) => format!(
+            "{}/{}/{}/{}{}",
+            ids.real,
+            ids.effective,
+            ids.saved,
+            ids.filesystem,
+            if ids.is_elevated() { "*" } else { "" }
+        ),
+        None => "-".to_string(),
+    }
+}
+
+fn ports_string(ports: &[u16]) -> String {
+    match ports.len() {
+        0 => "-------------------------".to_string(),
+        _ => format!("{:?}", ports).replace(['[', ']'], ""),
+    }
+}
+
+fn last_cpu_string(last_cpu: Option<i32>) -> String {
+    last_cpu
+        .map(|cpu| cpu.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+// Start time formatted like ps: the local time of day if it started within the last day, or the
+// month and day otherwise.
+fn start_time_string(start_time: u64, run_time_secs: u64) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let tm = match local_time(start_time) {
+        Some(tm) => tm,
+        None => return "-".to_string(),
+    };
+
+    if run_time_secs < 24 * 60 * 60 {
+        format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+    } else {
+        let month = MONTHS.get(tm.tm_mon as usize).unwrap_or(&"???");
+        format!("{}{:02}", month, tm.tm_mday)
+    }
+}
+
+// Local date and time, e.g. 2024-03-01 09:41.
+pub(crate) fn date_time_string(time: u64) -> String {
+    match local_time(time) {
+        Some(tm) => format!(
+            "{}-{:02}-{:02} {:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min
+        ),
+        None => "-".to_string(),
+    }
+}
+
+// Local date and time to the second, e.g. 2024-03-01 09:41:07.
+pub(crate) fn timestamp_string(time: u64) -> String {
+    match local_time(time) {
+        Some(tm) => format!(
+            "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        ),
+        None => "-".to_string(),
+    }
+}
+
+fn local_time(time: u64) -> Option<libc::tm> {
+    let time = time as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        None
+    } else {
+        Some(tm)
+    }
+}
+
+// Duration formatted like ps' etime: [[dd-]hh:]mm:ss.
+pub(crate) fn duration_string(secs: u64) -> String {
+    let (days, hours) = (secs / 86400, secs / 3600 % 24);
+    let (minutes, seconds) = (secs / 60 % 60, secs % 60);
+
+    if days > 0 {
+        format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
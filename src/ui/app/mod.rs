@@ -1,51 +1,846 @@
-use crate::core::process::ProcData;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::core::cgroup::CgroupData;
+use crate::core::container::ContainerInfo;
+use crate::core::cpu::CpuData;
+use crate::core::disk::DiskData;
+use crate::core::filesystem::FilesystemData;
+use crate::core::interrupts::{compare_irqs, InterruptData, IrqData, IrqSortColumn};
+use crate::core::memory::SystemMemory;
+use crate::core::namespace::NamespaceKind;
+use crate::core::network::InterfaceData;
+use crate::core::pressure::PressureData;
+use crate::core::process::{compare_processes, Pid, ProcData, SortColumn};
+use crate::core::sensors::SensorData;
+use crate::core::summary::SystemSummary;
+use crate::core::system_reader::SystemData;
+use crate::core::thread::{ThreadData, ThreadSelection};
+use crate::core::tree::{ProcTree, ProcTreeNode};
+use crate::core::vmstat::VmActivity;
+use crate::settings::{Settings, MAX_INTERVAL, MIN_INTERVAL};
+use crate::ui::theme::Theme;
+
+use self::columns::{IoMode, ProcColumn};
+
+pub mod columns;
 pub mod widgets;
 
+// Top-level views of the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Processes,
+    // Usage aggregated per cgroup (slice, scope or service).
+    Cgroups,
+    // Processes grouped by the namespace they belong to.
+    Namespaces,
+    // Traffic per network interface.
+    Network,
+    // Activity per block device.
+    Disks,
+    // Space and inode usage per mounted filesystem.
+    Filesystems,
+    // Temperature and fan readings.
+    Sensors,
+    // Paging, swapping and OOM kill counters.
+    Vm,
+    // Interrupt and softirq rates per CPU.
+    Interrupts,
+}
+
+// Restricts the process table to some of the processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcFilter {
+    // Processes in the cgroup or any of its descendants.
+    Cgroup(String),
+    // Processes in the given container.
+    Container(ContainerInfo),
+    // Processes in any container.
+    Containerised,
+    // The given processes.
+    Pids(Vec<Pid>),
+    // Processes whose real or effective user has the given name or uid.
+    User(String),
+}
+
+impl ProcFilter {
+    pub fn matches(&self, process: &ProcData) -> bool {
+        match self {
+            ProcFilter::Cgroup(path) => match &process.cgroup {
+                Some(cgroup) => {
+                    path == "/"
+                        || cgroup == path
+                        || cgroup
+                            .strip_prefix(path.as_str())
+                            .is_some_and(|rest| rest.starts_with('/'))
+                }
+                None => false,
+            },
+            ProcFilter::Container(container) => process.container.as_ref() == Some(container),
+            ProcFilter::Containerised => process.container.is_some(),
+            ProcFilter::Pids(pids) => pids.contains(&process.pid),
+            ProcFilter::User(user) => match user.parse::<u32>() {
+                Ok(uid) => process
+                    .uids
+                    .is_some_and(|uids| uids.real == uid || uids.effective == uid),
+                Err(_) => {
+                    process.user.as_ref() == Some(user)
+                        || process.effective_user.as_ref() == Some(user)
+                }
+            },
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            ProcFilter::Cgroup(path) => format!("cgroup {}", path),
+            ProcFilter::Container(container) => format!("container {}", container.label()),
+            ProcFilter::Containerised => "containers".to_string(),
+            ProcFilter::Pids(pids) => {
+                let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+                format!("pids {}", pids.join(", "))
+            }
+            ProcFilter::User(user) => format!("user {}", user),
+        }
+    }
+}
+
+// How threads are shown in the process table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadMode {
+    // Only processes, unless one has been expanded into its threads.
+    Processes,
+    // Every thread of every process as its own row.
+    AllThreads,
+}
+
+// A row of the namespaces view.
+pub enum NamespaceRow<'a> {
+    // A namespace (identified by its inode, if known) and the number of processes in it.
+    Namespace(Option<u64>, usize),
+    Process(&'a ProcData),
+}
+
+// A row of the VM activity view.
+pub struct VmRow {
+    pub label: &'static str,
+    // Events per second since the last tick; None for counters shown without a rate.
+    pub per_sec: Option<f64>,
+    pub total: u64,
+    // Whether the counter shows trouble right now, such as a swap storm.
+    pub alert: bool,
+}
+
+// Ticks an OOM kill stays flagged for after it happens, so it does not vanish at the next
+// refresh.
+const OOM_ALERT_TICKS: usize = 10;
+
+// Refresh intervals the refresh keys step through.
+const REFRESH_STEPS: [Duration; 11] = [
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(1500),
+    Duration::from_secs(2),
+    Duration::from_secs(3),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+];
+
+// A row of the process table.
+pub enum ProcRow<'a> {
+    // A process, and whether it has been expanded into its threads.
+    Process(&'a ProcData, bool),
+    Thread(&'a ThreadData),
+    // A process in the tree view, and whether its children are collapsed.
+    TreeNode(&'a ProcData, &'a ProcTreeNode, bool),
+}
+
 // The main application, containing the state
 pub struct App {
-    // All data per process
-    data: Vec<ProcData>,
-    // Network bits transmited
-    tx_bits_n: u64,
-    // Network bits received
-    rx_bits_n: u64,
+    // Last sample taken, shared with the collector that took it
+    sample: Arc<SystemData>,
+    // Indexes of the processes of the sample, in sort order
+    order: Vec<usize>,
+    // Why the last sample could not be taken, until one is
+    sample_error: Option<String>,
+    // Whether the loopback and bridge interfaces are left out
+    hide_virtual_interfaces: bool,
+    // Whether tmpfs and other in-memory filesystems are left out
+    hide_memory_filesystems: bool,
+    // Whether threads are listed flat or on demand
+    thread_mode: ThreadMode,
+    // Processes expanded into their threads
+    expanded: HashSet<Pid>,
+    // Column the process table is sorted by
+    sort_column: SortColumn,
+    // Whether the sort order is descending
+    sort_descending: bool,
+    // Columns shown in the process table, in order
+    columns: Vec<ProcColumn>,
+    // Whether I/O columns show rates or totals
+    io_mode: IoMode,
+    // Whether processes are shown as a tree of parents and children
+    tree_view: bool,
+    // Process tree built from the current data
+    tree: ProcTree,
+    // Processes whose children are hidden in the tree view
+    collapsed: HashSet<Pid>,
+    // View currently shown
+    view: View,
+    // Filter applied to the process table
+    filter: Option<ProcFilter>,
+    // Filters from the command line, applied on top of `filter` and never cleared
+    restrictions: Vec<ProcFilter>,
+    // Time between two refreshes
+    interval: Duration,
+    // Kind of namespace processes are grouped by in the namespaces view
+    namespace_kind: NamespaceKind,
+    // Ticks left before the last OOM kill stops being flagged
+    oom_alert_ticks: usize,
+    // Indexes of the interrupt sources of the sample in sort order, and how they are sorted
+    irq_order: Vec<usize>,
+    irq_sort_column: IrqSortColumn,
+    irq_sort_descending: bool,
+    // Colours tables and titles are drawn with
+    theme: Theme,
+    // Why the config file could not be reloaded, until it can
+    config_error: Option<String>,
 }
 
 impl App {
     #[allow(clippy::new_without_default)]
-    pub fn new(data: Vec<ProcData>, tx_n: u64, rx_n: u64) -> Self {
-        let data = data;
-        let tx_bits_n = tx_n;
-        let rx_bits_n = rx_n;
-        Self {
-            data,
-            tx_bits_n,
-            rx_bits_n,
-        }
+    pub fn new(data: Vec<ProcData>) -> Self {
+        let mut app = Self {
+            sample: Arc::new(SystemData {
+                processes: data,
+                ..Default::default()
+            }),
+            order: Vec::new(),
+            sample_error: None,
+            hide_virtual_interfaces: true,
+            hide_memory_filesystems: true,
+            thread_mode: ThreadMode::Processes,
+            expanded: HashSet::new(),
+            sort_column: SortColumn::Cpu,
+            sort_descending: true,
+            columns: ProcColumn::DEFAULT.to_vec(),
+            io_mode: IoMode::Rate,
+            tree_view: false,
+            tree: ProcTree::default(),
+            collapsed: HashSet::new(),
+            view: View::Processes,
+            filter: None,
+            restrictions: Vec::new(),
+            interval: Settings::default().interval,
+            namespace_kind: NamespaceKind::Pid,
+            oom_alert_ticks: 0,
+            irq_order: Vec::new(),
+            irq_sort_column: IrqSortColumn::Total,
+            irq_sort_descending: true,
+            theme: Theme::default(),
+            config_error: None,
+        };
+        app.sort();
+        app
     }
 
     pub fn data(&mut self) -> &Vec<ProcData> {
-        &self.data
+        &self.sample.processes
+    }
+
+    // Applies the options from the command line and the config file: refresh interval, sort,
+    // columns, colours and process filters.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.interval = settings.interval;
+        self.sort_column = settings.sort_column;
+        self.sort_descending = settings.sort_descending();
+        self.columns = settings.columns.clone();
+        self.theme = settings.theme;
+        self.restrictions.clear();
+        if !settings.pids.is_empty() {
+            self.restrictions
+                .push(ProcFilter::Pids(settings.pids.clone()));
+        }
+        if let Some(user) = &settings.user {
+            self.restrictions.push(ProcFilter::User(user.clone()));
+        }
+        self.sort();
+    }
+
+    // Applies what changed between `old`, the settings applied until now, and `new`, leaving
+    // the sort and refresh interval changed with the keys since alone unless they changed too.
+    pub fn apply_changed_settings(&mut self, old: &Settings, new: &Settings) {
+        if new.interval != old.interval {
+            self.interval = new.interval;
+        }
+        if new.sort_column != old.sort_column || new.sort_descending != old.sort_descending {
+            self.sort_column = new.sort_column;
+            self.sort_descending = new.sort_descending();
+        }
+        if new.columns != old.columns {
+            self.columns = new.columns.clone();
+        }
+        self.theme = new.theme;
+        self.sort();
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub fn config_error(&self) -> Option<&str> {
+        self.config_error.as_deref()
+    }
+
+    pub fn set_config_error(&mut self, err: Option<String>) {
+        self.config_error = err;
+    }
+
+    pub fn sample_error(&self) -> Option<&str> {
+        self.sample_error.as_deref()
+    }
+
+    // Keeps showing the last sample, with the reason the collector could not take a new one.
+    pub fn set_sample_error(&mut self, err: String) {
+        self.sample_error = Some(err);
+    }
+
+    pub fn restrictions(&self) -> &[ProcFilter] {
+        &self.restrictions
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    // Refreshes more often, down to the shortest interval.
+    pub fn speed_up_refresh(&mut self) {
+        self.interval = REFRESH_STEPS
+            .iter()
+            .rev()
+            .find(|step| **step < self.interval)
+            .copied()
+            .unwrap_or(MIN_INTERVAL);
+    }
+
+    // Refreshes less often, up to the longest interval.
+    pub fn slow_down_refresh(&mut self) {
+        self.interval = REFRESH_STEPS
+            .iter()
+            .find(|step| **step > self.interval)
+            .copied()
+            .unwrap_or(MAX_INTERVAL);
+    }
+
+    // Takes in a sample from the collector. The sample is shared rather than copied; the app
+    // only keeps the order it sorts and filters it in.
+    pub fn update(&mut self, sample: Arc<SystemData>) {
+        self.oom_alert_ticks = if sample.vm.new_oom_kills > 0 {
+            OOM_ALERT_TICKS
+        } else {
+            self.oom_alert_ticks.saturating_sub(1)
+        };
+        self.sample = sample;
+        self.sample_error = None;
+        self.sort();
+        self.sort_irqs();
+        let pids: HashSet<Pid> = self.sample.processes.iter().map(|p| p.pid).collect();
+        self.expanded.retain(|pid| pids.contains(pid));
+        self.collapsed.retain(|pid| pids.contains(pid));
+    }
+
+    pub fn cpu(&self) -> &CpuData {
+        &self.sample.cpu
+    }
+
+    pub fn memory(&self) -> &SystemMemory {
+        &self.sample.memory
+    }
+
+    pub fn summary(&self) -> &SystemSummary {
+        &self.sample.summary
+    }
+
+    pub fn pressure(&self) -> &PressureData {
+        &self.sample.pressure
+    }
+
+    pub fn vm(&self) -> &VmActivity {
+        &self.sample.vm
     }
 
-    pub fn update_data(&mut self, data: &Vec<ProcData>) -> () {
-        self.data = data.to_vec();
+    // Whether a process was killed by the OOM killer in the last few ticks.
+    pub fn oom_alert(&self) -> bool {
+        self.oom_alert_ticks > 0
     }
 
-    pub fn rx_bits(&self) -> &u64 {
-        &self.rx_bits_n
+    pub fn vm_rows(&self) -> Vec<VmRow> {
+        let vm = &self.sample.vm;
+        let totals = &vm.totals;
+        let swap_storm = vm.is_swap_storm();
+        let row = |label, per_sec, total, alert| VmRow {
+            label,
+            per_sec,
+            total,
+            alert,
+        };
+        vec![
+            row(
+                "Page faults",
+                Some(vm.page_faults_per_sec),
+                totals.page_faults,
+                false,
+            ),
+            row(
+                "Major page faults",
+                Some(vm.major_faults_per_sec),
+                totals.major_faults,
+                false,
+            ),
+            row(
+                "Pages swapped in",
+                Some(vm.swap_in_pages_per_sec),
+                totals.swap_in_pages,
+                swap_storm,
+            ),
+            row(
+                "Pages swapped out",
+                Some(vm.swap_out_pages_per_sec),
+                totals.swap_out_pages,
+                swap_storm,
+            ),
+            row(
+                "Pages scanned",
+                Some(vm.pages_scanned_per_sec),
+                totals.pages_scanned,
+                false,
+            ),
+            row(
+                "Pages reclaimed",
+                Some(vm.pages_stolen_per_sec),
+                totals.pages_stolen,
+                false,
+            ),
+            row("OOM kills", None, totals.oom_kills, self.oom_alert()),
+        ]
     }
 
-    pub fn update_rx_bits(&mut self, rx_bits: u64) -> () {
-        self.rx_bits_n = rx_bits;
+    pub fn interrupts(&self) -> &InterruptData {
+        &self.sample.interrupts
     }
 
-    pub fn tx_bits(&self) -> &u64 {
-        &self.tx_bits_n
+    // Interrupt sources in sort order.
+    pub fn irqs(&self) -> Vec<&IrqData> {
+        let irqs = &self.sample.interrupts.irqs;
+        self.irq_order.iter().map(|idx| &irqs[*idx]).collect()
     }
 
-    pub fn update_tx_bits(&mut self, tx_bits: u64) -> () {
-        self.tx_bits_n = tx_bits;
+    pub fn irq_sort_column(&self) -> (IrqSortColumn, bool) {
+        (self.irq_sort_column, self.irq_sort_descending)
+    }
+
+    // Cgroup the process table is filtered to, if any.
+    pub fn filtered_cgroup(&self) -> Option<&CgroupData> {
+        match &self.filter {
+            Some(ProcFilter::Cgroup(path)) => self.sample.cgroups.iter().find(|c| &c.path == path),
+            _ => None,
+        }
+    }
+
+    pub fn cgroups(&self) -> &[CgroupData] {
+        &self.sample.cgroups
+    }
+
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    // Switches to the given view, or back to the process table if it is already shown.
+    pub fn toggle_view(&mut self, view: View) {
+        self.view = if self.view == view {
+            View::Processes
+        } else {
+            view
+        };
+    }
+
+    pub fn namespace_kind(&self) -> NamespaceKind {
+        self.namespace_kind
+    }
+
+    // Groups the namespaces view by the next kind of namespace.
+    pub fn next_namespace_kind(&mut self) {
+        self.namespace_kind = self.namespace_kind.next();
+    }
+
+    pub fn filter(&self) -> Option<&ProcFilter> {
+        self.filter.as_ref()
+    }
+
+    // Shows the processes of the cgroup at the given row of the cgroup view.
+    pub fn drill_down(&mut self, row: usize) {
+        if let Some(cgroup) = self.sample.cgroups.get(row) {
+            self.filter = Some(ProcFilter::Cgroup(cgroup.path.clone()));
+            self.view = View::Processes;
+            self.sort();
+        }
+    }
+
+    // Shows only the processes in the same container as the process at the given row, or every
+    // containerised process if it runs on the host. Removes the filter if one is already set.
+    pub fn toggle_container_filter(&mut self, row: usize) {
+        if let Some(ProcFilter::Container(_) | ProcFilter::Containerised) = self.filter {
+            self.filter = None;
+            self.sort();
+            return;
+        }
+
+        let pid = match self.rows().get(row) {
+            Some(ProcRow::Process(process, _)) | Some(ProcRow::TreeNode(process, _, _)) => {
+                process.pid
+            }
+            Some(ProcRow::Thread(thread)) => thread.pid,
+            None => return,
+        };
+        let container = self
+            .sample
+            .processes
+            .iter()
+            .find(|process| process.pid == pid)
+            .and_then(|process| process.container.clone());
+        self.filter = Some(match container {
+            Some(container) => ProcFilter::Container(container),
+            None => ProcFilter::Containerised,
+        });
+        self.sort();
+    }
+
+    // Removes the process filter, going back to the cgroup view if it came from there.
+    pub fn clear_filter(&mut self) {
+        if let Some(ProcFilter::Cgroup(_)) = self.filter.take() {
+            self.view = View::Cgroups;
+        }
+        self.sort();
+    }
+
+    // Number of rows of the table shown in the current view.
+    pub fn row_count(&self) -> usize {
+        match self.view {
+            View::Processes => self.rows().len(),
+            View::Cgroups => self.sample.cgroups.len(),
+            View::Namespaces => self.namespace_rows().len(),
+            View::Network => self.interfaces().len(),
+            View::Disks => self.sample.disks.len(),
+            View::Filesystems => self.filesystems().len(),
+            View::Sensors => self.sample.sensors.len(),
+            View::Vm => self.vm_rows().len(),
+            View::Interrupts => self.irq_order.len(),
+        }
+    }
+
+    // Rows of the namespaces view: every namespace of the current kind followed by its
+    // processes, in the order of the process table.
+    pub fn namespace_rows(&self) -> Vec<NamespaceRow<'_>> {
+        let mut groups: BTreeMap<Option<u64>, Vec<&ProcData>> = BTreeMap::new();
+        for process in self.visible() {
+            groups
+                .entry(process.namespaces.get(self.namespace_kind))
+                .or_default()
+                .push(process);
+        }
+
+        let mut rows = Vec::with_capacity(self.order.len() + groups.len());
+        for (inode, processes) in groups {
+            rows.push(NamespaceRow::Namespace(inode, processes.len()));
+            rows.extend(processes.into_iter().map(NamespaceRow::Process));
+        }
+        rows
+    }
+
+    // Processes passing the filters, in sort order.
+    pub fn visible_processes(&self) -> Vec<&ProcData> {
+        self.visible().collect()
+    }
+
+    // Processes that pass the current filter and the command-line ones.
+    fn visible(&self) -> impl Iterator<Item = &ProcData> {
+        self.order
+            .iter()
+            .map(|idx| &self.sample.processes[*idx])
+            .filter(move |p| self.filter.as_ref().is_none_or(|f| f.matches(p)))
+            .filter(move |p| self.restrictions.iter().all(|r| r.matches(p)))
+    }
+
+    pub fn disks(&self) -> &[DiskData] {
+        &self.sample.disks
+    }
+
+    pub fn sensors(&self) -> &[SensorData] {
+        &self.sample.sensors
+    }
+
+    // Filesystems shown, leaving out in-memory ones if they are hidden.
+    pub fn filesystems(&self) -> Vec<&FilesystemData> {
+        self.sample
+            .filesystems
+            .iter()
+            .filter(|fs| !(self.hide_memory_filesystems && fs.is_memory_backed()))
+            .collect()
+    }
+
+    pub fn hide_memory_filesystems(&self) -> bool {
+        self.hide_memory_filesystems
+    }
+
+    pub fn toggle_memory_filesystems(&mut self) {
+        self.hide_memory_filesystems = !self.hide_memory_filesystems;
+    }
+
+    // Network interfaces shown, leaving out the loopback and bridges if they are hidden.
+    pub fn interfaces(&self) -> Vec<&InterfaceData> {
+        self.sample
+            .interfaces
+            .iter()
+            .filter(|interface| !(self.hide_virtual_interfaces && interface.is_virtual))
+            .collect()
+    }
+
+    pub fn hide_virtual_interfaces(&self) -> bool {
+        self.hide_virtual_interfaces
+    }
+
+    pub fn toggle_virtual_interfaces(&mut self) {
+        self.hide_virtual_interfaces = !self.hide_virtual_interfaces;
+    }
+
+    pub fn sort_column(&self) -> (SortColumn, bool) {
+        (self.sort_column, self.sort_descending)
+    }
+
+    // Sorts by the next sortable column shown in the table, or in the interrupts view.
+    pub fn next_sort_column(&mut self) {
+        match self.view {
+            View::Interrupts => self.step_irq_sort_column(1),
+            _ => self.step_sort_column(1),
+        }
+    }
+
+    // Sorts by the previous sortable column shown in the table, or in the interrupts view.
+    pub fn prev_sort_column(&mut self) {
+        match self.view {
+            View::Interrupts => self.step_irq_sort_column(-1),
+            _ => self.step_sort_column(-1),
+        }
+    }
+
+    fn sort(&mut self) {
+        let (processes, column, descending) = (
+            &self.sample.processes,
+            self.sort_column,
+            self.sort_descending,
+        );
+        self.order = (0..processes.len()).collect();
+        self.order
+            .sort_by(|a, b| compare_processes(&processes[*a], &processes[*b], column, descending));
+        self.tree = ProcTree::build(self.visible());
+    }
+
+    fn sort_irqs(&mut self) {
+        let (irqs, column, descending) = (
+            &self.sample.interrupts.irqs,
+            self.irq_sort_column,
+            self.irq_sort_descending,
+        );
+        self.irq_order = (0..irqs.len()).collect();
+        self.irq_order
+            .sort_by(|a, b| compare_irqs(&irqs[*a], &irqs[*b], column, descending));
+    }
+
+    fn step_sort_column(&mut self, step: isize) {
+        let sortable: Vec<SortColumn> = self
+            .columns
+            .iter()
+            .filter_map(|column| column.sort_column(self.io_mode))
+            .collect();
+        if sortable.is_empty() {
+            return;
+        }
+
+        let next = match sortable.iter().position(|c| *c == self.sort_column) {
+            Some(idx) => (idx as isize + step).rem_euclid(sortable.len() as isize) as usize,
+            None => 0,
+        };
+        self.sort_column = sortable[next];
+        self.sort();
+    }
+
+    // Cycles through the total rate, the rate on each CPU and the IRQ name.
+    fn step_irq_sort_column(&mut self, step: isize) {
+        let sortable: Vec<IrqSortColumn> = std::iter::once(IrqSortColumn::Total)
+            .chain((0..self.sample.interrupts.cpus.len()).map(IrqSortColumn::Cpu))
+            .chain(std::iter::once(IrqSortColumn::Name))
+            .collect();
+
+        let next = match sortable.iter().position(|c| *c == self.irq_sort_column) {
+            Some(idx) => (idx as isize + step).rem_euclid(sortable.len() as isize) as usize,
+            None => 0,
+        };
+        self.irq_sort_column = sortable[next];
+        self.sort_irqs();
+    }
+
+    pub fn reverse_sort(&mut self) {
+        if self.view == View::Interrupts {
+            self.irq_sort_descending = !self.irq_sort_descending;
+            self.sort_irqs();
+            return;
+        }
+        self.sort_descending = !self.sort_descending;
+        self.sort();
+    }
+
+    // Columns shown in the process table. The tree view moves the (indented) name next to the
+    // pid, followed by the subtree totals, so the tree stays visible on narrow terminals.
+    pub fn columns(&self) -> Vec<ProcColumn> {
+        if !self.tree_view {
+            return self.columns.clone();
+        }
+
+        let mut columns: Vec<ProcColumn> = self
+            .columns
+            .iter()
+            .copied()
+            .filter(|c| *c != ProcColumn::Name)
+            .collect();
+        let idx = columns
+            .iter()
+            .position(|c| *c == ProcColumn::Pid)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let tree_columns = std::iter::once(ProcColumn::Name).chain(ProcColumn::SUBTREE);
+        columns.splice(idx..idx, tree_columns);
+        columns
+    }
+
+    pub fn io_mode(&self) -> IoMode {
+        self.io_mode
+    }
+
+    // Switches I/O columns between rates and totals, keeping the sort on the same column.
+    pub fn toggle_io_mode(&mut self) {
+        self.io_mode = match self.io_mode {
+            IoMode::Rate => IoMode::Total,
+            IoMode::Total => IoMode::Rate,
+        };
+        self.sort_column = match self.sort_column {
+            SortColumn::DiskRead => SortColumn::DiskReadRate,
+            SortColumn::DiskReadRate => SortColumn::DiskRead,
+            SortColumn::DiskWrite => SortColumn::DiskWriteRate,
+            SortColumn::DiskWriteRate => SortColumn::DiskWrite,
+            column => column,
+        };
+        self.sort();
+    }
+
+    pub fn thread_mode(&self) -> ThreadMode {
+        self.thread_mode
+    }
+
+    pub fn toggle_thread_mode(&mut self) {
+        self.thread_mode = match self.thread_mode {
+            ThreadMode::Processes => ThreadMode::AllThreads,
+            ThreadMode::AllThreads => ThreadMode::Processes,
+        };
+    }
+
+    // Processes whose threads have to be read for the rows shown.
+    pub fn thread_selection(&self) -> ThreadSelection {
+        match self.thread_mode {
+            ThreadMode::AllThreads => ThreadSelection::All,
+            ThreadMode::Processes => ThreadSelection::Processes(self.expanded.clone()),
+        }
+    }
+
+    pub fn tree_view(&self) -> bool {
+        self.tree_view
+    }
+
+    pub fn toggle_tree_view(&mut self) {
+        self.tree_view = !self.tree_view;
+        self.thread_mode = ThreadMode::Processes;
+    }
+
+    // Expands the process at the given row into its threads, or collapses it back. In the
+    // tree view, shows or hides the children of the process instead.
+    pub fn toggle_expanded(&mut self, row: usize) {
+        match self.rows().get(row) {
+            Some(ProcRow::Process(process, _)) => {
+                let pid = process.pid;
+                if !self.expanded.remove(&pid) {
+                    self.expanded.insert(pid);
+                }
+            }
+            Some(ProcRow::TreeNode(process, _, _)) => {
+                let pid = process.pid;
+                if !self.collapsed.remove(&pid) {
+                    self.collapsed.insert(pid);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Hides (or shows) the children of the process at the given row in the tree view.
+    pub fn set_collapsed(&mut self, row: usize, collapsed: bool) {
+        let pid = match self.rows().get(row) {
+            Some(ProcRow::TreeNode(process, _, _)) => process.pid,
+            _ => return,
+        };
+
+        if collapsed {
+            self.collapsed.insert(pid);
+        } else {
+            self.collapsed.remove(&pid);
+        }
+    }
+
+    // Rows of the process table in display order.
+    pub fn rows(&self) -> Vec<ProcRow<'_>> {
+        match self.thread_mode {
+            ThreadMode::AllThreads => {
+                let mut threads: Vec<&ThreadData> =
+                    self.visible().flat_map(|p| p.threads.iter()).collect();
+                threads.sort_by(|a, b| b.cpu_usage_percent.total_cmp(&a.cpu_usage_percent));
+                threads.into_iter().map(ProcRow::Thread).collect()
+            }
+            ThreadMode::Processes if self.tree_view => {
+                let index: HashMap<Pid, &ProcData> = self.visible().map(|p| (p.pid, p)).collect();
+                self.tree
+                    .flatten(&self.collapsed)
+                    .into_iter()
+                    .filter_map(|node| {
+                        let process = index.get(&node.pid)?;
+                        let collapsed = self.collapsed.contains(&node.pid);
+                        Some(ProcRow::TreeNode(process, node, collapsed))
+                    })
+                    .collect()
+            }
+            ThreadMode::Processes => {
+                let mut rows = Vec::with_capacity(self.order.len());
+                for process in self.visible() {
+                    let expanded = self.expanded.contains(&process.pid);
+                    rows.push(ProcRow::Process(process, expanded));
+                    if expanded {
+                        rows.extend(process.threads.iter().map(ProcRow::Thread));
+                    }
+                }
+                rows
+            }
+        }
     }
 }
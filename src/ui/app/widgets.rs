@@ -1,12 +1,32 @@
+use std::time::Duration;
+
 use tui::backend::Backend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, BorderType, Borders, Cell, List, ListItem, Row, Table, TableState};
+use tui::widgets::{
+    Block, BorderType, Borders, Cell, List, ListItem, Paragraph, Row, Table, TableState,
+};
 use tui::Frame;
 
-use crate::core::process::ProcData;
-use crate::ui::app::App;
+use crate::core::cgroup::CgroupData;
+use crate::core::cpu::{CpuData, CpuUsage};
+use crate::core::disk::{DiskData, DiskKind};
+use crate::core::filesystem::FilesystemData;
+use crate::core::interrupts::{IrqData, IrqSortColumn};
+use crate::core::memory::SystemMemory;
+use crate::core::namespace::NamespaceKind;
+use crate::core::network::InterfaceData;
+use crate::core::pressure::{Pressure, PressureData, PressureLine};
+use crate::core::process::SortColumn;
+use crate::core::sensors::{SensorData, SensorKind};
+use crate::core::summary::SystemSummary;
+use crate::core::vmstat::VmActivity;
+use crate::ui::app::columns::{
+    date_time_string, duration_string, kb_rate_string, kb_string, IoMode, ProcColumn,
+};
+use crate::ui::app::{App, NamespaceRow, ProcRow, ThreadMode, View, VmRow};
+use crate::ui::theme::Theme;
 
 pub fn draw<B>(rect: &mut Frame<B>, _app: &mut App, proc_state: &mut TableState)
 where
@@ -16,136 +36,1274 @@ where
     if size.width > 138 && size.width < 165 {
         size.width = 138;
     }
+    let theme = &_app.theme();
+
     // Vertical layout
+    let cpu = _app.cpu();
+    let cpu_columns = cpu_meter_columns(cpu.cores.len());
+    let cpu_height = (1 + cpu.cores.len().div_ceil(cpu_columns) + 2) as u16;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(4), Constraint::Min(10)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(cpu_height.max(MEMORY_HEIGHT)),
+                Constraint::Length(SUMMARY_HEIGHT),
+                Constraint::Min(10),
+            ]
+            .as_ref(),
+        )
         .split(size);
+    let header = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(chunks[0]);
+
+    // CPU meters
+    draw_cpu(rect, header[0], cpu, cpu_columns, theme);
+
+    // Memory and swap meters
+    draw_memory(rect, header[1], _app.memory(), theme);
+
+    // Load, tasks and general network
+    let interfaces = _app.interfaces();
+    let pressure = match _app.filtered_cgroup() {
+        Some(cgroup) => (format!("Pressure in {}", cgroup.path), &cgroup.pressure),
+        None => ("Pressure".to_string(), _app.pressure()),
+    };
+    let mut alerts = vm_alerts(_app.vm(), _app.oom_alert());
+    if let Some(err) = _app.config_error() {
+        alerts.push(format!("CONFIG ERROR: {}", err));
+    }
+    if let Some(err) = _app.sample_error() {
+        alerts.push(format!("SAMPLE ERROR: {}", err));
+    }
+    let net_list = draw_summary(
+        _app.summary(),
+        &interfaces,
+        pressure,
+        alerts,
+        _app.interval(),
+        theme,
+    );
+    rect.render_widget(net_list, chunks[1]);
 
-    //General network
-    let net_list = draw_network_general(_app.tx_bits_n, _app.rx_bits_n);
-    rect.render_widget(net_list, chunks[0]);
+    match _app.view() {
+        View::Processes => {}
+        View::Cgroups => {
+            let cgroups = draw_cgroups(_app.cgroups(), theme);
+            rect.render_stateful_widget(cgroups, chunks[2], proc_state);
+            return;
+        }
+        View::Namespaces => {
+            let namespaces = draw_namespaces(_app.namespace_rows(), _app.namespace_kind(), theme);
+            rect.render_stateful_widget(namespaces, chunks[2], proc_state);
+            return;
+        }
+        View::Disks => {
+            let disks = draw_disks(_app.disks(), theme);
+            rect.render_stateful_widget(disks, chunks[2], proc_state);
+            return;
+        }
+        View::Filesystems => {
+            let filesystems =
+                draw_filesystems(&_app.filesystems(), _app.hide_memory_filesystems(), theme);
+            rect.render_stateful_widget(filesystems, chunks[2], proc_state);
+            return;
+        }
+        View::Interrupts => {
+            let cpus = &_app.interrupts().cpus;
+            let widths = interrupt_widths(cpus.len());
+            let table =
+                draw_interrupts((cpus, &_app.irqs()), &widths, _app.irq_sort_column(), theme);
+            rect.render_stateful_widget(table, chunks[2], proc_state);
+            return;
+        }
+        View::Vm => {
+            let vm = draw_vm(_app.vm_rows(), _app.vm(), theme);
+            rect.render_stateful_widget(vm, chunks[2], proc_state);
+            return;
+        }
+        View::Sensors => {
+            let sensors = draw_sensors(_app.sensors(), theme);
+            rect.render_stateful_widget(sensors, chunks[2], proc_state);
+            return;
+        }
+        View::Network => {
+            let network = draw_interfaces(&interfaces, _app.hide_virtual_interfaces(), theme);
+            rect.render_stateful_widget(network, chunks[2], proc_state);
+            return;
+        }
+    }
 
     // Process table
-    let process = draw_process(_app.data().to_vec());
-    rect.render_stateful_widget(process, chunks[1], proc_state);
+    let title = match (_app.thread_mode(), _app.tree_view()) {
+        (ThreadMode::AllThreads, _) => "All threads",
+        (ThreadMode::Processes, true) => "Process tree",
+        (ThreadMode::Processes, false) => "All process",
+    };
+    let restrictions: Vec<String> = _app
+        .restrictions()
+        .iter()
+        .map(|restriction| restriction.description())
+        .collect();
+    let title = if restrictions.is_empty() {
+        title.to_string()
+    } else {
+        format!("{} of {}", title, restrictions.join(" and "))
+    };
+    let title = match _app.filter() {
+        Some(filter) => format!("{} in {} (Esc to go back)", title, filter.description()),
+        None => title,
+    };
+    let columns = _app.columns();
+    let widths: Vec<Constraint> = columns
+        .iter()
+        .map(|column| Constraint::Min(column.width()))
+        .collect();
+    let process = draw_process(
+        _app.rows(),
+        title,
+        &columns,
+        &widths,
+        _app.io_mode(),
+        _app.sort_column(),
+        theme,
+    );
+    rect.render_stateful_widget(process, chunks[2], proc_state);
+}
+
+// CPU states shown in the meters, in the order their segments are drawn, with their colour.
+const CPU_STATES: [(&str, Color); 8] = [
+    ("usr", Color::Green),
+    ("nic", Color::Blue),
+    ("sys", Color::Red),
+    ("irq", Color::Magenta),
+    ("sirq", Color::LightMagenta),
+    ("iow", Color::DarkGray),
+    ("st", Color::Cyan),
+    ("gst", Color::Yellow),
+];
+
+fn cpu_states(usage: &CpuUsage) -> [f64; 8] {
+    [
+        usage.user,
+        usage.nice,
+        usage.system,
+        usage.irq,
+        usage.softirq,
+        usage.iowait,
+        usage.steal,
+        usage.guest + usage.guest_nice,
+    ]
+}
+
+// Number of columns the per-core meters are laid out in, so that up to 32 cores take at most
+// four lines.
+fn cpu_meter_columns(cores: usize) -> usize {
+    cores.div_ceil(4).clamp(1, 8)
+}
+
+fn draw_cpu<B>(rect: &mut Frame<B>, area: Rect, cpu: &CpuData, columns: usize, theme: &Theme)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(Span::styled(" CPU ", theme.header()))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    rect.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    // Average of every CPU, followed by the legend of the meters
+    let mut spans = cpu_meter("avg", &cpu.total, 30);
+    for ((name, color), percent) in CPU_STATES.iter().zip(cpu_states(&cpu.total)) {
+        spans.push(Span::styled(
+            format!("  {} {:.1}", name, percent),
+            Style::default().fg(*color),
+        ));
+    }
+    rect.render_widget(Paragraph::new(Spans::from(spans)), rows[0]);
+
+    let per_column = cpu.cores.len().div_ceil(columns);
+    if per_column == 0 {
+        return;
+    }
+    let column_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .split(rows[1]);
+    for (cores, area) in cpu.cores.chunks(per_column).zip(column_areas) {
+        // Room left for the bar after the label, brackets and percentage
+        let bar_width = (area.width as usize).saturating_sub(18);
+        let lines: Vec<Spans> = cores
+            .iter()
+            .map(|(id, usage)| Spans::from(cpu_meter(&format!("cpu{}", id), usage, bar_width)))
+            .collect();
+        rect.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+// A meter like `cpu3 [|||||||       ]  45.2%`, with a segment per CPU state.
+fn cpu_meter(label: &str, usage: &CpuUsage, width: usize) -> Vec<Span<'static>> {
+    let segments: Vec<(f64, Color)> = CPU_STATES
+        .iter()
+        .zip(cpu_states(usage))
+        .map(|((_, color), percent)| (percent, *color))
+        .collect();
+    meter(
+        label,
+        &segments,
+        width,
+        format!("{:5.1}%", usage.busy_percent()),
+    )
+}
+
+// A bar made of coloured segments given as percentages of its width, followed by `value`.
+fn meter(
+    label: &str,
+    segments: &[(f64, Color)],
+    width: usize,
+    value: String,
+) -> Vec<Span<'static>> {
+    let mut spans = vec![Span::raw(format!("{:<6}[", label))];
+
+    // Segment ends are rounded from the running total, so the bar never overflows
+    let mut filled = 0;
+    let mut cumulative = 0.0;
+    for (percent, color) in segments {
+        cumulative += percent;
+        let end = ((cumulative / 100.0 * width as f64).round() as usize).min(width);
+        if end > filled {
+            spans.push(Span::styled(
+                "|".repeat(end - filled),
+                Style::default().fg(*color),
+            ));
+            filled = end;
+        }
+    }
+
+    spans.push(Span::raw(" ".repeat(width - filled)));
+    spans.push(Span::raw(format!("] {}", value)));
+    spans
+}
+
+// Height of the memory panel: the memory and swap meters, two lines of details and borders.
+const MEMORY_HEIGHT: u16 = 6;
+
+fn draw_memory<B>(rect: &mut Frame<B>, area: Rect, memory: &SystemMemory, theme: &Theme)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(Span::styled(" Memory ", theme.header()))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    rect.render_widget(block, area);
+
+    let percent = |bytes: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            bytes as f64 / total as f64 * 100.0
+        }
+    };
+    // Room left for the bar after the label, brackets and sizes
+    let bar_width = (inner.width as usize).saturating_sub(24);
+
+    // Used memory, then buffers and page cache, which can be reclaimed
+    let total = memory.total_bytes;
+    let mem = meter(
+        "Mem",
+        &[
+            (percent(memory.used_bytes(), total), Color::Green),
+            (percent(memory.buffers_bytes, total), Color::Blue),
+            (percent(memory.cached_bytes, total), Color::Yellow),
+        ],
+        bar_width,
+        format!(
+            "{}/{}",
+            size_string(memory.used_bytes()),
+            size_string(total)
+        ),
+    );
+    let swap = meter(
+        "Swp",
+        &[(
+            percent(memory.swap_used_bytes(), memory.swap_total_bytes),
+            Color::Red,
+        )],
+        bar_width,
+        format!(
+            "{}/{}",
+            size_string(memory.swap_used_bytes()),
+            size_string(memory.swap_total_bytes)
+        ),
+    );
+
+    let detail = |name: &str, bytes: u64| format!("{} {}  ", name, size_string(bytes));
+    let lines = vec![
+        Spans::from(mem),
+        Spans::from(swap),
+        Spans::from(vec![
+            Span::styled(
+                detail("avail", memory.available_bytes),
+                Style::default().fg(Color::Green),
+            ),
+            Span::styled(
+                detail("buf", memory.buffers_bytes),
+                Style::default().fg(Color::Blue),
+            ),
+            Span::styled(
+                detail("cache", memory.cached_bytes),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw(detail("shmem", memory.shmem_bytes)),
+            Span::raw(detail("slab", memory.slab_bytes)),
+        ]),
+        Spans::from(vec![
+            Span::raw(detail("dirty", memory.dirty_bytes)),
+            Span::raw(detail("wback", memory.writeback_bytes)),
+            Span::raw(detail("swpcache", memory.swap_cached_bytes)),
+            Span::raw(format!(
+                "huge {}/{} x {}",
+                memory.huge_pages_total - memory.huge_pages_free.min(memory.huge_pages_total),
+                memory.huge_pages_total,
+                size_string(memory.huge_page_size_bytes)
+            )),
+        ]),
+    ];
+    rect.render_widget(Paragraph::new(lines), inner);
+}
+
+// Size with a binary unit suffix, e.g. 1.5G.
+fn size_string(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+// Problems with memory worth flagging in the header whichever view is shown.
+fn vm_alerts(vm: &VmActivity, oom_alert: bool) -> Vec<String> {
+    let mut alerts = Vec::new();
+    if vm.is_swap_storm() {
+        alerts.push("SWAP STORM".to_string());
+    }
+    if oom_alert {
+        alerts.push("OOM KILL".to_string());
+    }
+    alerts
 }
 
-fn draw_network_general<'a>(tx: u64, rx: u64) -> List<'static> {
+// Lines of the summary box, plus its borders.
+const SUMMARY_HEIGHT: u16 = 6;
+
+// Stall averages at or above which pressure is shown in yellow, and in red.
+const PRESSURE_WARNING_PERCENT: f64 = 10.0;
+const PRESSURE_CRITICAL_PERCENT: f64 = 25.0;
+
+fn draw_summary(
+    summary: &SystemSummary,
+    interfaces: &[&InterfaceData],
+    (pressure_label, pressure): (String, &PressureData),
+    alerts: Vec<String>,
+    interval: Duration,
+    theme: &Theme,
+) -> List<'static> {
     let rx_style = Style::default().fg(Color::LightMagenta);
     let tx_style = Style::default().fg(Color::LightCyan);
 
+    let rx_rate: f64 = interfaces.iter().map(|i| i.rx_bytes_per_sec).sum();
+    let tx_rate: f64 = interfaces.iter().map(|i| i.tx_bytes_per_sec).sum();
+    let rx_total: u64 = interfaces.iter().map(|i| i.totals.rx_bytes).sum();
+    let tx_total: u64 = interfaces.iter().map(|i| i.totals.tx_bytes).sum();
     let spans = Spans::from(vec![
-        Span::styled("Total Network RX: ", rx_style),
-        Span::styled(tx.to_string() + " Bytes", rx_style),
+        Span::styled("Network RX: ", rx_style),
+        Span::styled(
+            format!(
+                "{} KB/s ({} total)",
+                kb_rate_string(rx_rate),
+                size_string(rx_total)
+            ),
+            rx_style,
+        ),
         Span::raw("      "),
-        Span::styled("Total Network TX: ", tx_style),
-        Span::styled(rx.to_string() + " Bytes", tx_style),
+        Span::styled("Network TX: ", tx_style),
+        Span::styled(
+            format!(
+                "{} KB/s ({} total)",
+                kb_rate_string(tx_rate),
+                size_string(tx_total)
+            ),
+            tx_style,
+        ),
+    ]);
+
+    let alert_style = Style::default()
+        .fg(Color::White)
+        .bg(Color::Red)
+        .add_modifier(Modifier::BOLD);
+    let mut title = vec![Span::styled("  Rtop  ", theme.header())];
+    for alert in alerts {
+        title.push(Span::styled(format!(" {} ", alert), alert_style));
+        title.push(Span::raw(" "));
+    }
+
+    let label_style = theme.header();
+    let load = &summary.load;
+    let uptime = Spans::from(vec![
+        Span::styled("Up: ", label_style),
+        Span::raw(duration_string(summary.uptime_secs)),
+        Span::styled("   Booted: ", label_style),
+        Span::raw(date_time_string(summary.boot_time)),
+        Span::styled("   Load average: ", label_style),
+        Span::raw(format!(
+            "{:.2} {:.2} {:.2}",
+            load.one, load.five, load.fifteen
+        )),
+        Span::styled("   Refresh: ", label_style),
+        Span::raw(if interval < Duration::from_secs(1) {
+            format!("{}ms", interval.as_millis())
+        } else {
+            format!("{:.1}s", interval.as_secs_f64())
+        }),
     ]);
 
-    let title = Span::styled("  Rtop  ", Style::default().fg(Color::LightGreen));
+    let tasks = &summary.tasks;
+    let activity = Spans::from(vec![
+        Span::styled("Tasks: ", label_style),
+        Span::raw(format!(
+            "{} total, {} running, {} sleeping, {} stopped, {} zombie",
+            tasks.total, tasks.running, tasks.sleeping, tasks.stopped, tasks.zombie
+        )),
+        Span::styled("   Context switches: ", label_style),
+        Span::raw(format!("{:.0}/s", summary.context_switches_per_sec)),
+        Span::styled("   Interrupts: ", label_style),
+        Span::raw(format!("{:.0}/s", summary.interrupts_per_sec)),
+        Span::styled("   Forks: ", label_style),
+        Span::raw(format!("{:.1}/s", summary.forks_per_sec)),
+    ]);
 
-    let list_items = [ListItem::new(vec![spans])];
+    let list_items = [
+        ListItem::new(vec![uptime]),
+        ListItem::new(vec![activity]),
+        ListItem::new(vec![spans]),
+        ListItem::new(vec![pressure_spans(pressure_label, pressure, theme)]),
+    ];
     List::new(list_items)
         .block(
             Block::default()
-                .title(title)
+                .title(Spans::from(title))
                 .title_alignment(Alignment::Center)
                 .borders(Borders::ALL),
         )
         .style(Style::default().fg(Color::White))
 }
 
-fn draw_process(data: Vec<ProcData>) -> Table<'static> {
-    let blue_style = Style::default().fg(Color::LightCyan);
-    let white_style = Style::default().fg(Color::Gray);
+// PSI averages of CPU, memory and I/O, e.g. "CPU 3.64 3.48 2.59 | 0.00 0.00 0.00",
+// coloured by how long tasks were stalled over the last 10 seconds.
+fn pressure_spans(label: String, pressure: &PressureData, theme: &Theme) -> Spans<'static> {
+    let label_style = theme.header();
+    let mut spans = vec![Span::styled(
+        format!("{} (some | full, avg10 avg60 avg300): ", label),
+        label_style,
+    )];
+    if !pressure.is_available() {
+        spans.push(Span::raw("not available"));
+        return Spans::from(spans);
+    }
+
+    let resources = [
+        ("CPU", pressure.cpu),
+        ("Mem", pressure.memory),
+        ("IO", pressure.io),
+    ];
+    for (idx, (name, resource)) in resources.into_iter().enumerate() {
+        if idx > 0 {
+            spans.push(Span::raw("   "));
+        }
+        spans.push(Span::styled(format!("{} ", name), label_style));
+        match resource {
+            Some(resource) => spans.push(Span::styled(
+                pressure_string(&resource),
+                pressure_style(&resource, theme),
+            )),
+            None => spans.push(Span::raw("-")),
+        }
+    }
+    Spans::from(spans)
+}
 
-    let mut rows = vec![];
+fn pressure_string(pressure: &Pressure) -> String {
+    let line = |l: &PressureLine| format!("{:.2} {:.2} {:.2}", l.avg10, l.avg60, l.avg300);
+    match &pressure.full {
+        Some(full) => format!("{} | {}", line(&pressure.some), line(full)),
+        None => line(&pressure.some),
+    }
+}
 
-    for process in data.iter() {
-        let total_r_disk_write_kb = process.total_disk_read_bytes.unwrap_or(0) / 1000;
-        let total_w_disk_write_kb = process.total_disk_write_bytes.unwrap_or(0) / 1000;
+fn pressure_style(pressure: &Pressure, theme: &Theme) -> Style {
+    let worst = pressure
+        .full
+        .map_or(0.0, |full| full.avg10)
+        .max(pressure.some.avg10);
+    if worst >= PRESSURE_CRITICAL_PERCENT {
+        theme.critical()
+    } else if worst >= PRESSURE_WARNING_PERCENT {
+        theme.warning()
+    } else {
+        Style::default()
+    }
+}
 
-        let tcp_string = match process.tcp_ports.len() {
-            0 => "-------------------------".to_string(),
-            _ => format!("{:?}", process.tcp_ports).replace(&['[', ']'], ""),
-        };
+const CGROUP_WIDTHS: [Constraint; 10] = [
+    Constraint::Min(40),
+    Constraint::Min(6),
+    Constraint::Min(6),
+    Constraint::Min(12),
+    Constraint::Min(12),
+    Constraint::Min(12),
+    Constraint::Min(12),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(8),
+];
 
-        let udp_string = match process.udp_ports.len() {
-            0 => "-------------------------".to_string(),
-            _ => format!("{:?}", process.udp_ports).replace(&['[', ']'], ""),
-        };
+fn draw_cgroups(cgroups: &[CgroupData], theme: &Theme) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
 
-        let row = Row::new(vec![
-            Cell::from(Span::styled(process.pid.to_string(), blue_style)),
-            Cell::from(Span::styled(process.parent_pid.to_string(), white_style)),
-            Cell::from(Span::styled(process.priority.to_string(), blue_style)),
-            Cell::from(Span::styled(
-                format!("{:.4}", process.mem_usage_percent.to_string()),
-                white_style,
-            )),
-            Cell::from(Span::styled(
-                format!("{:.4}", process.cpu_usage_percent.to_string()),
-                blue_style,
-            )),
-            Cell::from(Span::styled(total_r_disk_write_kb.to_string(), white_style)),
-            Cell::from(Span::styled(total_w_disk_write_kb.to_string(), blue_style)),
-            Cell::from(Span::styled(process.state.0.clone(), white_style)),
-            Cell::from(Span::styled(process.uid.unwrap().to_string(), blue_style)),
-            Cell::from(Span::styled(process.name.to_string(), white_style)),
-            Cell::from(Span::styled(tcp_string, blue_style)),
-            Cell::from(Span::styled(udp_string, white_style)),
-            Cell::from(Span::styled(process.command.to_string(), blue_style)),
-        ]);
-        rows.push(row);
+    let rows: Vec<Row> = cgroups
+        .iter()
+        .map(|cgroup| {
+            let cells = [
+                format!("{}{}", "  ".repeat(cgroup.depth), cgroup.name()),
+                cgroup.total_process_count.to_string(),
+                format!("{:.2}", cgroup.cpu_usage_percent),
+                kb_string(cgroup.memory_current_bytes),
+                kb_string(cgroup.memory_max_bytes),
+                kb_rate_string(cgroup.io_read_bytes_per_sec),
+                kb_rate_string(cgroup.io_write_bytes_per_sec),
+                some_avg10_string(cgroup.pressure.cpu),
+                some_avg10_string(cgroup.pressure.memory),
+                some_avg10_string(cgroup.pressure.io),
+            ];
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = if idx.is_multiple_of(2) {
+                    accent_style
+                } else {
+                    text_style
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let headers = [
+        "CGROUP",
+        "PROCS",
+        "CPU%",
+        "MEM(KB)",
+        "MEM_MAX(KB)",
+        "READ(KB/s)",
+        "WRITE(KB/s)",
+        "CPU_PSI",
+        "MEM_PSI",
+        "IO_PSI",
+    ];
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title("Cgroups (Enter to show processes)"),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&CGROUP_WIDTHS)
+        .column_spacing(1)
+}
+
+// Share of the last 10 seconds some task of a cgroup was stalled on a resource.
+fn some_avg10_string(pressure: Option<Pressure>) -> String {
+    match pressure {
+        Some(pressure) => format!("{:.2}", pressure.some.avg10),
+        None => "-".to_string(),
     }
+}
+
+const NAMESPACE_WIDTHS: [Constraint; 8] = [
+    Constraint::Min(20),
+    Constraint::Min(7),
+    Constraint::Min(7),
+    Constraint::Min(20),
+    Constraint::Min(19),
+    Constraint::Min(10),
+    Constraint::Min(20),
+    Constraint::Min(1000),
+];
+
+fn draw_namespaces(
+    rows: Vec<NamespaceRow<'_>>,
+    kind: NamespaceKind,
+    theme: &Theme,
+) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let namespace_style = theme.header().add_modifier(Modifier::BOLD);
+
+    let rows: Vec<Row> = rows
+        .into_iter()
+        .map(|row| match row {
+            NamespaceRow::Namespace(inode, count) => {
+                let inode = inode.map_or_else(|| "?".to_string(), |inode| inode.to_string());
+                let cells = [
+                    format!("{}:[{}]", kind.name(), inode),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    format!("{} processes", count),
+                ];
+                Row::new(cells).style(namespace_style)
+            }
+            NamespaceRow::Process(process) => {
+                let ns_pid = process
+                    .ns_pids
+                    .last()
+                    .map_or_else(|| "-".to_string(), |pid| pid.to_string());
+                let chain = process
+                    .ns_pids
+                    .iter()
+                    .map(|pid| pid.to_string())
+                    .collect::<Vec<_>>()
+                    .join("→");
+                let cells = [
+                    String::new(),
+                    process.pid.to_string(),
+                    ns_pid,
+                    chain,
+                    process
+                        .container
+                        .as_ref()
+                        .map_or_else(|| "-".to_string(), |c| c.label()),
+                    process.user.clone().unwrap_or_else(|| "-".to_string()),
+                    process.name.clone(),
+                    process.command.clone(),
+                ];
+                Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                    let style = if idx.is_multiple_of(2) {
+                        accent_style
+                    } else {
+                        text_style
+                    };
+                    Cell::from(Span::styled(text, style))
+                }))
+            }
+        })
+        .collect();
+
+    let headers = [
+        "NAMESPACE",
+        "PID",
+        "NS_PID",
+        "NSPID_CHAIN",
+        "CONTAINER",
+        "USER",
+        "NAME",
+        "COMMAND",
+    ];
+    let title = format!("Processes by {} namespace (k to change)", kind.name());
 
     Table::new(rows)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Plain)
-                .title("All process"),
+                .title(title),
         )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Yellow)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&NAMESPACE_WIDTHS)
+        .column_spacing(1)
+}
+
+// Share of a source's interrupts above which the CPU handling them is highlighted, as a hint
+// that its affinity pins it to a single core.
+const IRQ_IMBALANCE_PERCENT: f64 = 75.0;
+
+// TYPE, IRQ and TOTAL, one column per CPU, then the description.
+fn interrupt_widths(num_cpus: usize) -> Vec<Constraint> {
+    let mut widths = vec![Constraint::Min(8), Constraint::Min(16), Constraint::Min(10)];
+    widths.extend(std::iter::repeat_n(Constraint::Min(9), num_cpus));
+    widths.push(Constraint::Min(1000));
+    widths
+}
+
+fn draw_interrupts<'a>(
+    (cpus, irqs): (&[usize], &[&IrqData]),
+    widths: &'a [Constraint],
+    (sort_column, sort_descending): (IrqSortColumn, bool),
+    theme: &Theme,
+) -> Table<'a> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let busiest_style = theme.warning().add_modifier(Modifier::BOLD);
+    let rate = |rate: f64| format!("{:.1}", rate);
+
+    let rows: Vec<Row> = irqs
+        .iter()
+        .map(|irq| {
+            let imbalanced = match irq.busiest_cpu() {
+                Some((idx, share)) if irq.per_cpu_per_sec.len() > 1 => {
+                    (share >= IRQ_IMBALANCE_PERCENT).then_some(idx)
+                }
+                _ => None,
+            };
+
+            let mut cells = vec![
+                Cell::from(Span::styled(irq.kind.name(), accent_style)),
+                Cell::from(Span::styled(irq.name.clone(), text_style)),
+                Cell::from(Span::styled(rate(irq.total_per_sec), accent_style)),
+            ];
+            cells.extend(
+                irq.per_cpu_per_sec
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, cpu_rate)| {
+                        let style = if imbalanced == Some(idx) {
+                            busiest_style
+                        } else {
+                            text_style
+                        };
+                        Cell::from(Span::styled(rate(*cpu_rate), style))
+                    }),
+            );
+            cells.push(Cell::from(Span::styled(
+                irq.description.clone(),
+                accent_style,
+            )));
+            Row::new(cells)
+        })
+        .collect();
+
+    let arrow = if sort_descending { "▼" } else { "▲" };
+    let header = |label: String, column: IrqSortColumn| {
+        if column == sort_column {
+            format!("{}{}", label, arrow)
+        } else {
+            label
+        }
+    };
+    let mut headers = vec![
+        "TYPE".to_string(),
+        header("IRQ".to_string(), IrqSortColumn::Name),
+        header("TOTAL/s".to_string(), IrqSortColumn::Total),
+    ];
+    headers.extend(
+        cpus.iter()
+            .enumerate()
+            .map(|(idx, cpu)| header(format!("CPU{}/s", cpu), IrqSortColumn::Cpu(idx))),
+    );
+    headers.push("DESCRIPTION".to_string());
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title("Interrupts (s to sort)"),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(widths)
+        .column_spacing(1)
+}
+
+const VM_WIDTHS: [Constraint; 3] = [
+    Constraint::Min(24),
+    Constraint::Min(14),
+    Constraint::Min(16),
+];
+
+fn draw_vm(rows: Vec<VmRow>, vm: &VmActivity, theme: &Theme) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let alert_style = theme.critical();
+
+    let rows: Vec<Row> = rows
+        .into_iter()
+        .map(|row| {
+            let cells = [
+                row.label.to_string(),
+                row.per_sec
+                    .map_or_else(|| "-".to_string(), |rate| format!("{:.1}", rate)),
+                row.total.to_string(),
+            ];
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = if row.alert {
+                    alert_style
+                } else if idx.is_multiple_of(2) {
+                    accent_style
+                } else {
+                    text_style
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let title = match vm.reclaim_efficiency_percent() {
+        Some(efficiency) => format!("VM activity (reclaim efficiency {:.0}%)", efficiency),
+        None => "VM activity".to_string(),
+    };
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title(title),
         )
+        .highlight_style(theme.selection())
         .header(
-            Row::new(vec![
-                "PID",
-                "PAID",
-                "PRI",
-                "MEM%",
-                "CPU%",
-                "READ(KB)",
-                "WRITE(KB)",
-                "STATE",
-                "UID",
-                "NAME",
-                "TCP_PORTS",
-                "UDP_PORTS",
-                "COMMAND",
-            ])
-            .style(Style::default().fg(Color::LightGreen))
-            .bottom_margin(1),
+            Row::new(["COUNTER", "RATE(/s)", "TOTAL"])
+                .style(theme.header())
+                .bottom_margin(1),
         )
-        .widths(&[
-            Constraint::Min(5),
-            Constraint::Min(5),
-            Constraint::Min(5),
-            Constraint::Min(5),
-            Constraint::Min(5),
-            Constraint::Min(10),
-            Constraint::Min(10),
-            Constraint::Min(10),
-            Constraint::Min(5),
-            Constraint::Min(15),
-            Constraint::Min(25),
-            Constraint::Min(25),
-            Constraint::Min(1000),
-        ])
+        .widths(&VM_WIDTHS)
         .column_spacing(1)
 }
+
+const SENSOR_WIDTHS: [Constraint; 5] = [
+    Constraint::Min(16),
+    Constraint::Min(24),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(10),
+];
+
+fn draw_sensors(sensors: &[SensorData], theme: &Theme) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let reading = |kind: SensorKind, value: Option<f64>| match (kind, value) {
+        (SensorKind::Temperature, Some(value)) => format!("{:.1}°C", value),
+        (SensorKind::Fan, Some(value)) => format!("{:.0} RPM", value),
+        (_, None) => "-".to_string(),
+    };
+
+    let rows: Vec<Row> = sensors
+        .iter()
+        .map(|sensor| {
+            let cells = [
+                sensor.chip.clone(),
+                sensor.label.clone(),
+                reading(sensor.kind, Some(sensor.value)),
+                reading(sensor.kind, sensor.high),
+                reading(sensor.kind, sensor.critical),
+            ];
+            let row_style = if sensor.is_critical() {
+                Some(theme.critical())
+            } else if sensor.is_high() {
+                Some(theme.warning())
+            } else {
+                None
+            };
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = match row_style {
+                    Some(style) => style,
+                    None if idx.is_multiple_of(2) => accent_style,
+                    None => text_style,
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let headers = ["CHIP", "SENSOR", "VALUE", "HIGH", "CRIT/MIN"];
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title("Sensors"),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&SENSOR_WIDTHS)
+        .column_spacing(1)
+}
+
+// Usage above which a filesystem is shown in yellow, and in red.
+const FILESYSTEM_WARNING_PERCENT: f64 = 80.0;
+const FILESYSTEM_CRITICAL_PERCENT: f64 = 90.0;
+
+const FILESYSTEM_WIDTHS: [Constraint; 10] = [
+    Constraint::Min(24),
+    Constraint::Min(20),
+    Constraint::Min(8),
+    Constraint::Min(9),
+    Constraint::Min(9),
+    Constraint::Min(9),
+    Constraint::Min(6),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(6),
+];
+
+fn draw_filesystems(
+    filesystems: &[&FilesystemData],
+    hide_memory: bool,
+    theme: &Theme,
+) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let threshold_style = |percent: f64| {
+        if percent >= FILESYSTEM_CRITICAL_PERCENT {
+            Some(theme.critical())
+        } else if percent >= FILESYSTEM_WARNING_PERCENT {
+            Some(theme.warning())
+        } else {
+            None
+        }
+    };
+
+    let rows: Vec<Row> = filesystems
+        .iter()
+        .map(|fs| {
+            let used_percent = fs.used_percent();
+            let inodes_percent = fs.used_inodes_percent();
+            let cells = [
+                fs.mount_point.display().to_string(),
+                fs.source.clone(),
+                fs.fs_type.clone(),
+                size_string(fs.total_bytes),
+                size_string(fs.used_bytes),
+                size_string(fs.available_bytes),
+                format!("{:.1}", used_percent),
+                fs.total_inodes.to_string(),
+                fs.used_inodes.to_string(),
+                format!("{:.1}", inodes_percent),
+            ];
+            let row_style = threshold_style(used_percent.max(inodes_percent));
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = match row_style {
+                    Some(style) => style,
+                    None if idx.is_multiple_of(2) => accent_style,
+                    None => text_style,
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let headers = [
+        "MOUNT", "SOURCE", "TYPE", "SIZE", "USED", "AVAIL", "USE%", "INODES", "IUSED", "IUSE%",
+    ];
+    let title = if hide_memory {
+        "Filesystems (T to show tmpfs)"
+    } else {
+        "Filesystems (T to hide tmpfs)"
+    };
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title(title),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&FILESYSTEM_WIDTHS)
+        .column_spacing(1)
+}
+
+const DISK_WIDTHS: [Constraint; 11] = [
+    Constraint::Min(24),
+    Constraint::Min(9),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(7),
+    Constraint::Min(6),
+    Constraint::Min(9),
+];
+
+fn draw_disks(disks: &[DiskData], theme: &Theme) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+
+    let rows: Vec<Row> = disks
+        .iter()
+        .map(|disk| {
+            let (name, kind) = match (disk.kind, &disk.label) {
+                (Some(DiskKind::Partition), _) => (format!("  {}", disk.name), "part"),
+                (Some(DiskKind::DeviceMapper), Some(label)) => {
+                    (format!("{} ({})", disk.name, label), "dm")
+                }
+                (Some(DiskKind::DeviceMapper), None) => (disk.name.clone(), "dm"),
+                _ => (disk.name.clone(), "disk"),
+            };
+            let cells = [
+                name,
+                kind.to_string(),
+                format!("{:.1}", disk.reads_per_sec),
+                format!("{:.1}", disk.writes_per_sec),
+                kb_rate_string(disk.read_bytes_per_sec),
+                kb_rate_string(disk.write_bytes_per_sec),
+                format!("{:.2}", disk.read_await_ms),
+                format!("{:.2}", disk.write_await_ms),
+                format!("{:.2}", disk.queue_depth),
+                format!("{:.1}", disk.utilization_percent),
+                disk.totals.in_flight.to_string(),
+            ];
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = if idx.is_multiple_of(2) {
+                    accent_style
+                } else {
+                    text_style
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let headers = [
+        "DEVICE",
+        "KIND",
+        "r/s",
+        "w/s",
+        "rKB/s",
+        "wKB/s",
+        "r_await",
+        "w_await",
+        "aqu-sz",
+        "%util",
+        "IN_FLIGHT",
+    ];
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title("Block devices"),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&DISK_WIDTHS)
+        .column_spacing(1)
+}
+
+const INTERFACE_WIDTHS: [Constraint; 11] = [
+    Constraint::Min(16),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(10),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(8),
+    Constraint::Min(10),
+    Constraint::Min(10),
+];
+
+fn draw_interfaces(
+    interfaces: &[&InterfaceData],
+    hide_virtual: bool,
+    theme: &Theme,
+) -> Table<'static> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let error_style = Style::default().fg(theme.critical);
+
+    let rows: Vec<Row> = interfaces
+        .iter()
+        .map(|interface| {
+            let totals = &interface.totals;
+            let name = if interface.is_virtual {
+                format!("{} (virtual)", interface.name)
+            } else {
+                interface.name.clone()
+            };
+            let cells = [
+                name,
+                kb_rate_string(interface.rx_bytes_per_sec),
+                kb_rate_string(interface.tx_bytes_per_sec),
+                format!("{:.1}", interface.rx_packets_per_sec),
+                format!("{:.1}", interface.tx_packets_per_sec),
+                totals.rx_errors.to_string(),
+                totals.tx_errors.to_string(),
+                totals.rx_drops.to_string(),
+                totals.tx_drops.to_string(),
+                size_string(totals.rx_bytes),
+                size_string(totals.tx_bytes),
+            ];
+            let has_errors =
+                totals.rx_errors + totals.tx_errors + totals.rx_drops + totals.tx_drops > 0;
+            Row::new(cells.into_iter().enumerate().map(|(idx, text)| {
+                let style = match idx {
+                    5..=8 if has_errors => error_style,
+                    _ if idx.is_multiple_of(2) => accent_style,
+                    _ => text_style,
+                };
+                Cell::from(Span::styled(text, style))
+            }))
+        })
+        .collect();
+
+    let headers = [
+        "INTERFACE",
+        "RX(KB/s)",
+        "TX(KB/s)",
+        "RX_PKT/s",
+        "TX_PKT/s",
+        "RX_ERR",
+        "TX_ERR",
+        "RX_DROP",
+        "TX_DROP",
+        "RX_TOTAL",
+        "TX_TOTAL",
+    ];
+    let title = if hide_virtual {
+        "Network interfaces (l to show loopback and bridges)"
+    } else {
+        "Network interfaces (l to hide loopback and bridges)"
+    };
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title(title),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(&INTERFACE_WIDTHS)
+        .column_spacing(1)
+}
+
+fn draw_process<'a>(
+    data: Vec<ProcRow<'_>>,
+    title: String,
+    columns: &[ProcColumn],
+    widths: &'a [Constraint],
+    io_mode: IoMode,
+    (sort_column, sort_descending): (SortColumn, bool),
+    theme: &Theme,
+) -> Table<'a> {
+    let accent_style = theme.accent();
+    let text_style = theme.text();
+    let style = |idx: usize| {
+        if idx.is_multiple_of(2) {
+            accent_style
+        } else {
+            text_style
+        }
+    };
+
+    let rows: Vec<Row> = data
+        .into_iter()
+        .map(|row| {
+            Row::new(columns.iter().enumerate().map(|(idx, column)| {
+                let text = match row {
+                    ProcRow::Process(process, expanded) => {
+                        column.process_cell(process, expanded, io_mode)
+                    }
+                    ProcRow::Thread(thread) => column.thread_cell(thread),
+                    ProcRow::TreeNode(process, node, collapsed) => {
+                        column.tree_cell(process, node, collapsed, io_mode)
+                    }
+                };
+                Cell::from(Span::styled(text, style(idx)))
+            }))
+        })
+        .collect();
+
+    let headers = columns.iter().map(|column| {
+        let header = column.header(io_mode);
+        if column.sort_column(io_mode) == Some(sort_column) {
+            let arrow = if sort_descending { "▼" } else { "▲" };
+            format!("{}{}", header, arrow)
+        } else {
+            header.to_string()
+        }
+    });
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .title(title),
+        )
+        .highlight_style(theme.selection())
+        .header(Row::new(headers).style(theme.header()).bottom_margin(1))
+        .widths(widths)
+        .column_spacing(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+
+    use super::*;
+    use crate::core::process::ProcData;
+
+    fn header_line(width: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, 40)).unwrap();
+        let mut app = App::new(vec![ProcData {
+            pid: 1,
+            name: "init".to_string(),
+            command: "/sbin/init".to_string(),
+            ..Default::default()
+        }]);
+        let mut state = TableState::default();
+        terminal
+            .draw(|rect| draw(rect, &mut app, &mut state))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect::<String>()
+            })
+            .find(|line| line.contains("PID"))
+            .unwrap()
+    }
+
+    #[test]
+    fn default_columns_fit_the_terminal() {
+        for width in [138, 250] {
+            let header = header_line(width);
+            for title in ["NAME", "TCP", "UDP", "COMMAND"] {
+                assert!(header.contains(title), "{} missing at {}", title, width);
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::core::error::RTopError;
+use crate::core::record::{read_recording, Recorder};
+use crate::core::summary::SystemSummary;
+use crate::core::system_reader::{SystemData, SystemReader};
+use crate::settings::Settings;
+use crate::ui::app::columns::{duration_string, timestamp_string, IoMode, ProcColumn};
+use crate::ui::app::App;
+
+// Columns a recording keeps. Replays print the configured columns among them, or all of them
+// if there are none.
+const RECORDED_COLUMNS: [ProcColumn; 13] = [
+    ProcColumn::Pid,
+    ProcColumn::ParentPid,
+    ProcColumn::User,
+    ProcColumn::Priority,
+    ProcColumn::State,
+    ProcColumn::Cpu,
+    ProcColumn::Mem,
+    ProcColumn::DiskRead,
+    ProcColumn::DiskWrite,
+    ProcColumn::NetReceivedRate,
+    ProcColumn::NetSentRate,
+    ProcColumn::Name,
+    ProcColumn::Command,
+];
+
+// Prints the process table every interval, like `top -b`, until `iterations` samples have
+// been printed or forever.
+pub fn run_batch(
+    mut reader: SystemReader,
+    settings: &Settings,
+    iterations: Option<u64>,
+) -> Result<(), RTopError> {
+    let mut app = App::new(Vec::new());
+    app.apply_settings(settings);
+    let mut out = io::stdout().lock();
+
+    // Rates need a previous sample to be computed from
+    reader.read_process_data()?;
+    let mut printed = 0;
+    while iterations.is_none_or(|n| printed < n) {
+        thread::sleep(settings.interval);
+        app.update(Arc::new(reader.read_process_data()?));
+        let heading = summary_heading(app.summary());
+        if output_closed(print_sample(&mut out, &heading, &settings.columns, &app))? {
+            break;
+        }
+        printed += 1;
+    }
+
+    Ok(())
+}
+
+// Writes a sample of the process list to `path` every interval, until `iterations` samples
+// have been written or forever.
+pub fn run_record(
+    mut reader: SystemReader,
+    settings: &Settings,
+    path: &Path,
+    iterations: Option<u64>,
+) -> Result<(), RTopError> {
+    let mut app = App::new(Vec::new());
+    app.apply_settings(settings);
+    let mut recorder = Recorder::new(BufWriter::new(File::create(path)?))?;
+
+    reader.read_process_data()?;
+    let mut written = 0;
+    while iterations.is_none_or(|n| written < n) {
+        thread::sleep(settings.interval);
+        app.update(Arc::new(reader.read_process_data()?));
+        recorder.write_sample(now_secs(), app.visible_processes())?;
+        written += 1;
+    }
+
+    Ok(())
+}
+
+// Prints the samples of a recording as batch mode does, waiting between them as long as
+// they were apart when recorded unless `no_delay` is set.
+pub fn run_replay(path: &Path, settings: &Settings, no_delay: bool) -> Result<(), RTopError> {
+    let samples = read_recording(BufReader::new(File::open(path)?))?;
+    let mut app = App::new(Vec::new());
+    app.apply_settings(settings);
+    let mut out = io::stdout().lock();
+    let mut columns: Vec<ProcColumn> = settings
+        .columns
+        .iter()
+        .copied()
+        .filter(|column| RECORDED_COLUMNS.contains(column))
+        .collect();
+    if columns.is_empty() {
+        columns = RECORDED_COLUMNS.to_vec();
+    }
+
+    let mut prev_time: Option<f64> = None;
+    for sample in samples {
+        if let (Some(prev), false) = (prev_time, no_delay) {
+            thread::sleep(Duration::from_secs_f64((sample.time - prev).max(0.0)));
+        }
+        prev_time = Some(sample.time);
+
+        app.update(Arc::new(SystemData {
+            processes: sample.processes,
+            ..Default::default()
+        }));
+        let heading = format!(
+            "rtop - {} (replay of {})",
+            timestamp_string(sample.time as u64),
+            path.display()
+        );
+        if output_closed(print_sample(&mut out, &heading, &columns, &app))? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn summary_heading(summary: &SystemSummary) -> String {
+    let load = &summary.load;
+    let tasks = &summary.tasks;
+    format!(
+        "rtop - {}  up {}  load average: {:.2} {:.2} {:.2}  tasks: {} total, {} running",
+        timestamp_string(now_secs() as u64),
+        duration_string(summary.uptime_secs),
+        load.one,
+        load.five,
+        load.fifteen,
+        tasks.total,
+        tasks.running
+    )
+}
+
+fn print_sample(
+    out: &mut impl Write,
+    heading: &str,
+    columns: &[ProcColumn],
+    app: &App,
+) -> io::Result<()> {
+    writeln!(out, "{}", heading)?;
+    let headers: Vec<String> = columns
+        .iter()
+        .map(|column| pad(column.header(IoMode::Rate), *column))
+        .collect();
+    writeln!(out, "{}", headers.join(" ").trim_end())?;
+
+    for process in app.visible_processes() {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| match column {
+                // Without the marker the table shows on processes with threads to expand
+                ProcColumn::Name => pad(&process.name, *column),
+                _ => pad(&column.process_cell(process, false, IoMode::Rate), *column),
+            })
+            .collect();
+        writeln!(out, "{}", cells.join(" ").trim_end())?;
+    }
+    writeln!(out)?;
+    out.flush()
+}
+
+fn pad(text: &str, column: ProcColumn) -> String {
+    format!("{:<width$}", text, width = column.width() as usize)
+}
+
+// Whether the output was closed by the command it is piped to exiting early (e.g.
+// `rtop batch | head`), which ends the run quietly.
+fn output_closed(result: io::Result<()>) -> io::Result<bool> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(true),
+        Err(err) => Err(err),
+    }
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
@@ -1,6 +1,11 @@
+use std::sync::Arc;
+
+use crate::core::error::RTopError;
+use crate::core::system_reader::SystemData;
+
 pub enum InputEvent<I> {
     /// An input event occurred.
     Input(I),
-    /// An tick event occurred.
-    Tick,
+    /// The collector took a new sample.
+    Sample(Result<Arc<SystemData>, RTopError>),
 }
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use crossterm::event::KeyCode;
+
+// What a key press does in the interactive monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    SortNext,
+    SortPrev,
+    ReverseSort,
+    RefreshFaster,
+    RefreshSlower,
+    ToggleIoMode,
+    ToggleThreads,
+    ToggleTree,
+    Collapse,
+    Expand,
+    Cgroups,
+    Namespaces,
+    Network,
+    Disks,
+    Filesystems,
+    Sensors,
+    Vm,
+    Interrupts,
+    ToggleMemoryFilesystems,
+    ToggleVirtualInterfaces,
+    NextNamespaceKind,
+    ContainerFilter,
+    ClearFilter,
+    Select,
+    Down,
+    Up,
+}
+
+impl Action {
+    pub const ALL: [Action; 27] = [
+        Action::Quit,
+        Action::SortNext,
+        Action::SortPrev,
+        Action::ReverseSort,
+        Action::RefreshFaster,
+        Action::RefreshSlower,
+        Action::ToggleIoMode,
+        Action::ToggleThreads,
+        Action::ToggleTree,
+        Action::Collapse,
+        Action::Expand,
+        Action::Cgroups,
+        Action::Namespaces,
+        Action::Network,
+        Action::Disks,
+        Action::Filesystems,
+        Action::Sensors,
+        Action::Vm,
+        Action::Interrupts,
+        Action::ToggleMemoryFilesystems,
+        Action::ToggleVirtualInterfaces,
+        Action::NextNamespaceKind,
+        Action::ContainerFilter,
+        Action::ClearFilter,
+        Action::Select,
+        Action::Down,
+        Action::Up,
+    ];
+
+    // Name of the action in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::SortNext => "sort-next",
+            Action::SortPrev => "sort-prev",
+            Action::ReverseSort => "reverse-sort",
+            Action::RefreshFaster => "refresh-faster",
+            Action::RefreshSlower => "refresh-slower",
+            Action::ToggleIoMode => "io-mode",
+            Action::ToggleThreads => "threads",
+            Action::ToggleTree => "tree",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+            Action::Cgroups => "cgroups",
+            Action::Namespaces => "namespaces",
+            Action::Network => "network",
+            Action::Disks => "disks",
+            Action::Filesystems => "filesystems",
+            Action::Sensors => "sensors",
+            Action::Vm => "vm",
+            Action::Interrupts => "interrupts",
+            Action::ToggleMemoryFilesystems => "memory-filesystems",
+            Action::ToggleVirtualInterfaces => "virtual-interfaces",
+            Action::NextNamespaceKind => "namespace-kind",
+            Action::ContainerFilter => "container",
+            Action::ClearFilter => "clear-filter",
+            Action::Select => "select",
+            Action::Down => "down",
+            Action::Up => "up",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::Quit => &[KeyCode::Char('q'), KeyCode::Char('c')],
+            Action::SortNext => &[KeyCode::Char('s')],
+            Action::SortPrev => &[KeyCode::Char('S')],
+            Action::ReverseSort => &[KeyCode::Char('r')],
+            Action::RefreshFaster => &[KeyCode::Char('+'), KeyCode::Char('=')],
+            Action::RefreshSlower => &[KeyCode::Char('-')],
+            Action::ToggleIoMode => &[KeyCode::Char('i')],
+            Action::ToggleThreads => &[KeyCode::Char('H')],
+            Action::ToggleTree => &[KeyCode::Char('t')],
+            Action::Collapse => &[KeyCode::Left],
+            Action::Expand => &[KeyCode::Right],
+            Action::Cgroups => &[KeyCode::Char('g')],
+            Action::Namespaces => &[KeyCode::Char('n')],
+            Action::Network => &[KeyCode::Char('N')],
+            Action::Disks => &[KeyCode::Char('D')],
+            Action::Filesystems => &[KeyCode::Char('F')],
+            Action::Sensors => &[KeyCode::Char('e')],
+            Action::Vm => &[KeyCode::Char('v')],
+            Action::Interrupts => &[KeyCode::Char('I')],
+            Action::ToggleMemoryFilesystems => &[KeyCode::Char('T')],
+            Action::ToggleVirtualInterfaces => &[KeyCode::Char('l')],
+            Action::NextNamespaceKind => &[KeyCode::Char('k')],
+            Action::ContainerFilter => &[KeyCode::Char('C')],
+            Action::ClearFilter => &[KeyCode::Esc],
+            Action::Select => &[KeyCode::Enter],
+            Action::Down => &[KeyCode::Down],
+            Action::Up => &[KeyCode::Up],
+        }
+    }
+}
+
+// Which action each key triggers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: HashMap<KeyCode, Action>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .flat_map(|action| action.default_keys().iter().map(move |key| (*key, action)))
+            .collect();
+        KeyMap { bindings }
+    }
+}
+
+impl KeyMap {
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+
+    // Replaces the keys of an action. A key already bound to another action is taken from it.
+    pub fn bind(&mut self, action: Action, keys: &[KeyCode]) {
+        self.bindings.retain(|_, bound| *bound != action);
+        for key in keys {
+            self.bindings.insert(*key, action);
+        }
+    }
+}
+
+// Parses a key as written in the config file: a single character ("q", "+") or the name of a
+// special key ("up", "enter", "esc", "f5", ...).
+pub fn parse_key(text: &str) -> Option<KeyCode> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    let name = text.to_lowercase();
+    let key = match name.as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        _ => {
+            let number = name.strip_prefix('f')?.parse::<u8>().ok()?;
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            KeyCode::F(number)
+        }
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(parse_key("q"), Some(KeyCode::Char('q')));
+        assert_eq!(parse_key("+"), Some(KeyCode::Char('+')));
+        assert_eq!(parse_key("Enter"), Some(KeyCode::Enter));
+        assert_eq!(parse_key("space"), Some(KeyCode::Char(' ')));
+        assert_eq!(parse_key("f5"), Some(KeyCode::F(5)));
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("ctrl"), None);
+    }
+
+    #[test]
+    fn binding_replaces_keys() {
+        let mut keys = KeyMap::default();
+        keys.bind(Action::Quit, &[KeyCode::Char('s')]);
+
+        assert_eq!(keys.action(KeyCode::Char('s')), Some(Action::Quit));
+        assert_eq!(keys.action(KeyCode::Char('q')), None);
+        assert_eq!(keys.action(KeyCode::Char('S')), Some(Action::SortPrev));
+    }
+}
@@ -1,28 +1,49 @@
 use std::borrow::BorrowMut;
 use std::io;
-use std::sync::mpsc::{self, Receiver};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
+    cursor,
+    event::{self, Event, KeyEvent},
     execute,
 };
+use signal_hook::consts::SIGHUP;
 
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use tui::widgets::TableState;
 use tui::{backend::CrosstermBackend, Terminal};
 
+use crate::config::SettingsSource;
+use crate::core::collector::Collector;
 use crate::core::error::RTopError;
 use crate::core::system_reader::SystemReader;
+use crate::settings::Settings;
 use crate::ui::app::widgets;
-use crate::ui::app::App;
+use crate::ui::app::{App, View};
+use crate::ui::keys::Action;
 
 use super::inputs::InputEvent;
 
-pub fn start_ui(mut sys_data: SystemReader) -> Result<(), RTopError> {
+// Runs the interactive monitor until it is quit. The system is sampled on a collector thread,
+// so this one only handles input and draws. The settings are loaded again from `source`
+// whenever its config file changes or rtop receives SIGHUP.
+pub fn start_ui(
+    sys_data: SystemReader,
+    settings: &Settings,
+    source: &SettingsSource,
+) -> Result<(), RTopError> {
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGHUP, Arc::clone(&reload))?;
+    let mut config_modified = source.config_modified();
+    // Settings last loaded, to tell what a reload changes
+    let mut settings = settings.clone();
+
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
+    let _restore = RestoreTerminal;
     crossterm::terminal::enable_raw_mode()?;
 
     let backend = CrosstermBackend::new(stdout);
@@ -30,13 +51,16 @@ pub fn start_ui(mut sys_data: SystemReader) -> Result<(), RTopError> {
     terminal.clear()?;
     terminal.hide_cursor()?;
 
-    //read input thread
-    let rxinput = input_thread(Duration::from_millis(1500));
+    // Key presses and samples arrive on the same channel, in the order they happen
+    let (tx, rxinput) = mpsc::channel();
+    input_thread(tx.clone());
+    let collector = Collector::spawn(sys_data, settings.interval, move |sample| {
+        tx.send(InputEvent::Sample(sample)).is_ok()
+    });
 
-    let data = sys_data.read_process_data()?;
-    let rx_n = data.net_received_bytes;
-    let tx_n = data.net_sent_bytes;
-    let mut app = App::new(data.processes, tx_n, rx_n);
+    let mut app = App::new(Vec::new());
+    app.apply_settings(&settings);
+    let mut thread_selection = app.thread_selection();
 
     let mut proc_table_state: TableState = TableState::default();
     proc_table_state.select(Some(0));
@@ -49,78 +73,205 @@ pub fn start_ui(mut sys_data: SystemReader) -> Result<(), RTopError> {
 
         //Wait for input
         match rxinput.recv()? {
-            InputEvent::Input(event) => match event.code {
-                KeyCode::Char('q') => {
+            InputEvent::Input(event) => match settings.keys.action(event.code) {
+                Some(Action::Quit) => {
                     break;
                 }
-                KeyCode::Char('c') => {
-                    break;
+                Some(Action::SortNext) => {
+                    a.next_sort_column();
+                }
+                Some(Action::SortPrev) => {
+                    a.prev_sort_column();
+                }
+                Some(Action::RefreshFaster) => {
+                    a.speed_up_refresh();
+                    collector.set_interval(a.interval());
+                }
+                Some(Action::RefreshSlower) => {
+                    a.slow_down_refresh();
+                    collector.set_interval(a.interval());
+                }
+                Some(Action::ReverseSort) => {
+                    a.reverse_sort();
+                }
+                Some(Action::ToggleIoMode) => {
+                    a.toggle_io_mode();
+                }
+                Some(Action::ToggleThreads) => {
+                    a.toggle_thread_mode();
+                    table_state.select(Some(0));
+                }
+                Some(Action::ToggleTree) => {
+                    a.toggle_tree_view();
+                    table_state.select(Some(0));
+                }
+                Some(Action::Collapse) => {
+                    if let Some(selected) = table_state.selected() {
+                        a.set_collapsed(selected, true);
+                    }
+                }
+                Some(Action::Expand) => {
+                    if let Some(selected) = table_state.selected() {
+                        a.set_collapsed(selected, false);
+                    }
+                }
+                Some(Action::Cgroups) => {
+                    a.toggle_view(View::Cgroups);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Namespaces) => {
+                    a.toggle_view(View::Namespaces);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Network) => {
+                    a.toggle_view(View::Network);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Disks) => {
+                    a.toggle_view(View::Disks);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Filesystems) => {
+                    a.toggle_view(View::Filesystems);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Sensors) => {
+                    a.toggle_view(View::Sensors);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Vm) => {
+                    a.toggle_view(View::Vm);
+                    table_state.select(Some(0));
+                }
+                Some(Action::Interrupts) => {
+                    a.toggle_view(View::Interrupts);
+                    table_state.select(Some(0));
+                }
+                Some(Action::ToggleMemoryFilesystems) => {
+                    a.toggle_memory_filesystems();
+                    table_state.select(Some(0));
+                }
+                Some(Action::ToggleVirtualInterfaces) => {
+                    a.toggle_virtual_interfaces();
+                    table_state.select(Some(0));
+                }
+                Some(Action::NextNamespaceKind) => {
+                    a.next_namespace_kind();
+                    table_state.select(Some(0));
                 }
-                KeyCode::Down => {
+                Some(Action::ContainerFilter) => {
                     if let Some(selected) = table_state.selected() {
-                        if selected >= a.data().len() - 1 {
+                        a.toggle_container_filter(selected);
+                        table_state.select(Some(0));
+                    }
+                }
+                Some(Action::ClearFilter) => {
+                    a.clear_filter();
+                    table_state.select(Some(0));
+                }
+                Some(Action::Select) => {
+                    if let Some(selected) = table_state.selected() {
+                        if a.view() == View::Cgroups {
+                            a.drill_down(selected);
+                            table_state.select(Some(0));
+                        } else {
+                            a.toggle_expanded(selected);
+                        }
+                    }
+                }
+                Some(Action::Down) => {
+                    if let Some(selected) = table_state.selected() {
+                        if selected + 1 >= a.row_count() {
                             table_state.select(Some(0));
                         } else {
                             table_state.select(Some(selected + 1));
                         }
                     }
                 }
-                KeyCode::Up => {
+                Some(Action::Up) => {
                     if let Some(selected) = table_state.selected() {
                         if selected > 0 {
                             table_state.select(Some(selected - 1));
                         } else {
-                            table_state.select(Some(a.data().len() - 1));
+                            table_state.select(Some(a.row_count().saturating_sub(1)));
                         }
                     }
                 }
-                _ => {}
+                None => {}
             },
-            InputEvent::Tick => {
-                // Update data
-                let data = sys_data.read_process_data()?;
-                a.update_data(&data.processes);
-                // Update tx/rx network bytes
-                a.update_rx_bits(data.net_received_bytes);
-                a.update_tx_bits(data.net_sent_bytes);
+            InputEvent::Sample(sample) => {
+                // Reload the config file when it changed or on SIGHUP
+                let modified = source.config_modified();
+                if reload.swap(false, Ordering::Relaxed) || modified != config_modified {
+                    config_modified = modified;
+                    match source.load() {
+                        Ok(new_settings) => {
+                            a.apply_changed_settings(&settings, &new_settings);
+                            a.set_config_error(None);
+                            if new_settings.use_current_cpu_total != settings.use_current_cpu_total
+                            {
+                                collector
+                                    .set_use_current_cpu_total(new_settings.use_current_cpu_total);
+                            }
+                            if new_settings.interval != settings.interval {
+                                collector.set_interval(new_settings.interval);
+                            }
+                            settings = new_settings;
+                        }
+                        Err(err) => a.set_config_error(Some(err.err_msg)),
+                    }
+                }
+
+                // Update data, or keep the last sample and tell why there is no new one
+                match sample {
+                    Ok(data) => {
+                        a.update(data);
+                        if let Some(selected) = table_state.selected() {
+                            table_state.select(Some(selected.min(a.row_count().saturating_sub(1))));
+                        }
+                    }
+                    Err(err) => a.set_sample_error(err.err_msg),
+                }
             }
         }
 
+        // Read the threads of the processes expanded since, or of all of them in thread mode
+        let selection = a.thread_selection();
+        if selection != thread_selection {
+            collector.select_threads(selection.clone());
+            thread_selection = selection;
+        }
+
         // Render
         terminal.draw(|rect| widgets::draw(rect, a, table_state))?;
     }
 
     terminal.clear()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-    crossterm::terminal::disable_raw_mode()?;
     Ok(())
 }
 
-pub fn input_thread(tick_rate: Duration) -> Receiver<InputEvent<KeyEvent>> {
-    let (tx, rx) = mpsc::channel();
-    thread::spawn(move || {
-        let mut last_tick = Instant::now();
-        loop {
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
-
-            if event::poll(timeout).expect("Event poll does not work") {
-                if let Ok(event_read) = event::read() {
-                    if let Event::Key(key) = event_read {
-                        tx.send(InputEvent::Input(key)).expect("Key does not send");
-                    }
-                }
-            }
+// Puts the terminal back the way it was when dropped, so that it is usable again however the
+// monitor stops, including on an error.
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+    }
+}
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(InputEvent::Tick) {
-                    last_tick = Instant::now();
+// Sends key presses until the receiving end is gone.
+pub fn input_thread(tx: Sender<InputEvent<KeyEvent>>) {
+    thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) => {
+                if tx.send(InputEvent::Input(key)).is_err() {
+                    return;
                 }
             }
+            Ok(_) => {}
+            Err(_) => return,
         }
     });
-
-    rx
 }
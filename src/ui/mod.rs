@@ -1,3 +1,6 @@
 pub mod app;
+pub mod batch;
 pub mod inputs;
+pub mod keys;
 pub mod layout;
+pub mod theme;
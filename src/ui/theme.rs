@@ -0,0 +1,121 @@
+use tui::style::{Color, Modifier, Style};
+
+// Colours of the tables, titles and alerts. The CPU and memory meters keep their own colours,
+// which tell the kinds of usage apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    // Every other cell of a table row
+    pub text: Color,
+    pub accent: Color,
+    // Table headers, panel titles and labels
+    pub header: Color,
+    // Selected row
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    // Values getting high, and values past their limit
+    pub warning: Color,
+    pub critical: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: Color::Gray,
+            accent: Color::LightCyan,
+            header: Color::LightGreen,
+            selection_fg: Color::Black,
+            selection_bg: Color::Yellow,
+            warning: Color::Yellow,
+            critical: Color::LightRed,
+        }
+    }
+}
+
+impl Theme {
+    pub fn text(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+
+    pub fn accent(&self) -> Style {
+        Style::default().fg(self.accent)
+    }
+
+    pub fn header(&self) -> Style {
+        Style::default().fg(self.header)
+    }
+
+    pub fn selection(&self) -> Style {
+        Style::default()
+            .bg(self.selection_bg)
+            .fg(self.selection_fg)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn warning(&self) -> Style {
+        Style::default().fg(self.warning)
+    }
+
+    pub fn critical(&self) -> Style {
+        Style::default()
+            .fg(self.critical)
+            .add_modifier(Modifier::BOLD)
+    }
+}
+
+// Parses a colour name as tui spells it ("lightcyan", "light-cyan" or "LightCyan"), a
+// "#rrggbb" hex code or a 0-255 palette index.
+pub fn parse_color(text: &str) -> Option<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+    if let Ok(index) = text.parse::<u8>() {
+        return Some(Color::Indexed(index));
+    }
+
+    let name: String = text
+        .chars()
+        .filter(|c| *c != '-' && *c != '_' && *c != ' ')
+        .collect::<String>()
+        .to_lowercase();
+    let color = match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("lightcyan"), Some(Color::LightCyan));
+        assert_eq!(parse_color("Light-Cyan"), Some(Color::LightCyan));
+        assert_eq!(parse_color("grey"), Some(Color::Gray));
+        assert_eq!(parse_color("#d7af00"), Some(Color::Rgb(0xd7, 0xaf, 0x00)));
+        assert_eq!(parse_color("214"), Some(Color::Indexed(214)));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("purple"), None);
+    }
+}
@@ -0,0 +1,510 @@
+// Reads the captured procfs and sysfs trees under tests/fixtures through a RootedSource, so
+// every collector sees the same files whatever machine the tests run on.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use rtop::core::disk::DiskKind;
+use rtop::core::network::INode;
+use rtop::core::process::ProcData;
+use rtop::core::sensors::{read_sensors, SensorKind};
+use rtop::core::sock_diag::SocketBytes;
+use rtop::core::source::{DataSource, RootedSource};
+use rtop::core::system_reader::{SystemData, SystemReader};
+use rtop::core::thread::ThreadSelection;
+
+fn fixtures() -> RootedSource {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    RootedSource::new(dir.join("proc"), dir.join("sys"))
+}
+
+// The fixtures with some of their /proc files missing.
+struct Missing {
+    fixtures: RootedSource,
+    paths: &'static [&'static str],
+}
+
+impl Missing {
+    fn reader(paths: &'static [&'static str]) -> SystemReader {
+        let source = Missing {
+            fixtures: fixtures(),
+            paths,
+        };
+        SystemReader::new(Box::new(source), false)
+    }
+}
+
+impl DataSource for Missing {
+    fn proc_root(&self) -> &Path {
+        self.fixtures.proc_root()
+    }
+
+    fn sys_root(&self) -> &Path {
+        self.fixtures.sys_root()
+    }
+
+    fn read_proc(&self, path: &str) -> io::Result<String> {
+        if self.paths.contains(&path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        self.fixtures.read_proc(path)
+    }
+}
+
+// The fixtures with TCP byte counters in place of the host's sock_diag: one dump per tick, in
+// order, None standing for a dump that fails.
+struct Sockets {
+    fixtures: RootedSource,
+    dumps: Mutex<VecDeque<Option<HashMap<INode, SocketBytes>>>>,
+}
+
+impl Sockets {
+    fn reader(dumps: Vec<Option<HashMap<INode, SocketBytes>>>) -> SystemReader {
+        let source = Sockets {
+            fixtures: fixtures(),
+            dumps: Mutex::new(dumps.into()),
+        };
+        SystemReader::new(Box::new(source), false)
+    }
+}
+
+// A dump with the counters of socket 4242, the worker's listening socket.
+fn dump(received: u64, sent: u64) -> Option<HashMap<INode, SocketBytes>> {
+    Some(HashMap::from([(4242, SocketBytes { received, sent })]))
+}
+
+impl DataSource for Sockets {
+    fn proc_root(&self) -> &Path {
+        self.fixtures.proc_root()
+    }
+
+    fn sys_root(&self) -> &Path {
+        self.fixtures.sys_root()
+    }
+
+    fn tcp_socket_bytes(&self) -> io::Result<HashMap<INode, SocketBytes>> {
+        match self.dumps.lock().unwrap().pop_front() {
+            Some(Some(dump)) => Ok(dump),
+            _ => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+// A copy of the fixtures in a temporary directory, for the tests that change them between
+// two ticks. Removed when dropped.
+struct TempFixtures {
+    root: PathBuf,
+}
+
+impl TempFixtures {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("rtop-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        copy_tree(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+            &root,
+        );
+        TempFixtures { root }
+    }
+
+    fn proc_path(&self, path: &str) -> PathBuf {
+        self.root.join("proc").join(path)
+    }
+
+    fn reader(&self) -> SystemReader {
+        let source = RootedSource::new(self.root.join("proc"), self.root.join("sys"));
+        SystemReader::new(Box::new(source), false)
+    }
+}
+
+impl Drop for TempFixtures {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// Copies a directory, keeping symlinks (such as fds) as they are.
+fn copy_tree(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type().unwrap();
+        if file_type.is_symlink() {
+            symlink(fs::read_link(entry.path()).unwrap(), &target).unwrap();
+        } else if file_type.is_dir() {
+            copy_tree(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), &target).unwrap();
+        }
+    }
+}
+
+fn process(data: &SystemData, pid: i32) -> &ProcData {
+    data.processes.iter().find(|p| p.pid == pid).unwrap()
+}
+
+fn read_fixtures() -> SystemData {
+    let mut reader = SystemReader::new(Box::new(fixtures()), false);
+    reader.read_process_data().unwrap()
+}
+
+#[test]
+fn reads_processes() {
+    let data = read_fixtures();
+
+    let mut pids: Vec<i32> = data.processes.iter().map(|p| p.pid).collect();
+    pids.sort_unstable();
+    assert_eq!(pids, [1, 42]);
+
+    let worker = data.processes.iter().find(|p| p.pid == 42).unwrap();
+    assert_eq!(worker.parent_pid, 1);
+    assert_eq!(worker.name, "worker");
+    assert_eq!(worker.command, "worker --jobs 4");
+    assert_eq!(worker.state.1, 'R');
+    assert_eq!(worker.pss_bytes, Some(3000 * 1024));
+    assert_eq!(worker.uids.map(|uids| uids.real), Some(1000));
+    assert_eq!(worker.start_time, 1_700_000_000 + 5000 / 100);
+    assert_eq!(worker.tcp_ports, [8080]);
+    assert_eq!(worker.udp_ports, [5353]);
+
+    // Without smaps_rollup, the memory breakdown comes from statm, which has no USS or PSS
+    let init = data.processes.iter().find(|p| p.pid == 1).unwrap();
+    let page_size = procfs::page_size().unwrap();
+    assert_eq!(init.uss_bytes, None);
+    assert_eq!(init.pss_bytes, None);
+    assert_eq!(init.shared_mem_bytes, Some(1000 * page_size));
+    assert_eq!(init.anon_mem_bytes, Some(500 * page_size));
+}
+
+#[test]
+fn reads_system_counters() {
+    let data = read_fixtures();
+
+    assert_eq!(data.memory.total_bytes, 8_000_000 * 1024);
+    assert_eq!(data.memory.available_bytes, 5_000_000 * 1024);
+    assert_eq!(data.cpu.cores.len(), 2);
+    assert_eq!(data.summary.load.one, 0.52);
+    assert_eq!(data.summary.uptime_secs, 12345);
+    assert_eq!(data.summary.boot_time, 1_700_000_000);
+    assert_eq!(data.summary.tasks.total, 2);
+    assert_eq!(data.vm.totals.pages_scanned, 600);
+    assert_eq!(data.vm.totals.oom_kills, 1);
+    assert_eq!(
+        data.pressure.memory.map(|memory| memory.some.avg10),
+        Some(1.5)
+    );
+
+    assert_eq!(data.interrupts.cpus, [0, 1]);
+    let names: Vec<&str> = data
+        .interrupts
+        .irqs
+        .iter()
+        .map(|irq| irq.name.as_str())
+        .collect();
+    assert_eq!(names, ["24", "LOC", "ERR", "HI", "NET_RX"]);
+}
+
+#[test]
+fn reads_devices() {
+    let data = read_fixtures();
+
+    let interfaces: Vec<(&str, bool)> = data
+        .interfaces
+        .iter()
+        .map(|interface| (interface.name.as_str(), interface.is_virtual))
+        .collect();
+    assert_eq!(interfaces, [("eth0", false), ("lo", true)]);
+    assert_eq!(data.interfaces[0].totals.rx_bytes, 1234);
+
+    // loop0 has never been used
+    let disks: Vec<(&str, Option<DiskKind>)> = data
+        .disks
+        .iter()
+        .map(|disk| (disk.name.as_str(), disk.kind))
+        .collect();
+    assert_eq!(
+        disks,
+        [
+            ("sda", Some(DiskKind::Disk)),
+            ("sda1", Some(DiskKind::Partition))
+        ]
+    );
+
+    // The ext4 root is hidden by the overlay mounted over it, /etc is the overlay again, and
+    // the NFS export answers in time
+    let filesystems: Vec<(&str, &str, &str)> = data
+        .filesystems
+        .iter()
+        .map(|fs| {
+            let mount_point = fs.mount_point.to_str().unwrap();
+            (mount_point, fs.source.as_str(), fs.fs_type.as_str())
+        })
+        .collect();
+    assert_eq!(
+        filesystems,
+        [
+            ("/", "overlay", "overlay"),
+            ("/tmp", "server:/export", "nfs4")
+        ]
+    );
+}
+
+#[test]
+fn reads_sensors() {
+    let sensors = read_sensors(&fixtures());
+
+    let summary: Vec<(&str, &str, SensorKind, f64)> = sensors
+        .iter()
+        .map(|s| (s.chip.as_str(), s.label.as_str(), s.kind, s.value))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("coretemp", "Package id 0", SensorKind::Temperature, 45.0),
+            ("coretemp", "fan1", SensorKind::Fan, 1200.0),
+            ("thermal", "x86_pkg_temp", SensorKind::Temperature, 52.0),
+        ]
+    );
+    assert_eq!(sensors[0].high, Some(80.0));
+    assert_eq!(sensors[0].critical, Some(100.0));
+    assert_eq!(sensors[2].critical, Some(105.0));
+}
+
+#[test]
+fn keeps_process_details_across_ticks() {
+    let mut reader = SystemReader::new(Box::new(fixtures()), false);
+    reader.select_threads(ThreadSelection::Processes([42].into()));
+    reader.read_process_data().unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let worker = data.processes.iter().find(|p| p.pid == 42).unwrap();
+    assert_eq!(worker.command, "worker --jobs 4");
+    assert_eq!(worker.uids.map(|uids| uids.real), Some(1000));
+    assert_eq!(worker.tcp_ports, [8080]);
+    assert_eq!(worker.num_threads, 2);
+    let mut threads: Vec<&str> = worker.threads.iter().map(|t| t.name.as_str()).collect();
+    threads.sort_unstable();
+    assert_eq!(threads, ["worker", "worker-io"]);
+
+    let init = data.processes.iter().find(|p| p.pid == 1).unwrap();
+    assert!(init.threads.is_empty());
+}
+
+#[test]
+fn samples_without_meminfo() {
+    let data = Missing::reader(&["meminfo"]).read_process_data().unwrap();
+
+    assert_eq!(data.memory.total_bytes, 0);
+    assert_eq!(data.processes.len(), 2);
+    assert!(data.processes.iter().all(|p| p.mem_usage_percent == 0.0));
+    assert_eq!(data.cpu.cores.len(), 2);
+}
+
+#[test]
+fn samples_without_vmstat() {
+    let mut reader = Missing::reader(&["vmstat"]);
+    reader.read_process_data().unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    assert_eq!(data.vm.totals.page_faults, 0);
+    assert_eq!(data.vm.page_faults_per_sec, 0.0);
+    assert_eq!(data.processes.len(), 2);
+    assert!(data.memory.total_bytes > 0);
+}
+
+#[test]
+fn samples_without_loadavg_and_uptime() {
+    let data = Missing::reader(&["loadavg", "uptime"])
+        .read_process_data()
+        .unwrap();
+
+    assert_eq!(data.summary.load.one, 0.0);
+    assert_eq!(data.summary.uptime_secs, 0);
+    assert_eq!(data.summary.boot_time, 1_700_000_000);
+    assert_eq!(data.summary.tasks.total, 2);
+}
+
+#[test]
+fn fixtures_have_no_socket_bytes() {
+    // Whatever sockets the host has, they are not those of the fixture processes
+    let data = read_fixtures();
+
+    assert!(data
+        .processes
+        .iter()
+        .all(|p| p.total_net_received_bytes == 0 && p.total_net_sent_bytes == 0));
+}
+
+#[test]
+fn adds_up_socket_bytes_per_process() {
+    let mut reader = Sockets::reader(vec![dump(1000, 10), dump(2000, 20)]);
+    reader.read_process_data().unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let worker = data.processes.iter().find(|p| p.pid == 42).unwrap();
+    assert_eq!(worker.total_net_received_bytes, 2000);
+    assert_eq!(worker.total_net_sent_bytes, 20);
+    assert!(worker.net_received_bytes_per_sec > 0.0);
+    let init = data.processes.iter().find(|p| p.pid == 1).unwrap();
+    assert_eq!(init.total_net_received_bytes, 0);
+}
+
+#[test]
+fn rescans_the_fds_of_processes_that_opened_or_closed_some() {
+    let fixtures = TempFixtures::new("moved-socket");
+    let mut reader = fixtures.reader();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).tcp_ports, [8080]);
+    assert!(process(&data, 1).tcp_ports.is_empty());
+
+    // The listening socket is handed over to init; the socket tables stay the same
+    fs::remove_file(fixtures.proc_path("42/fd/3")).unwrap();
+    fs::create_dir(fixtures.proc_path("1/fd")).unwrap();
+    symlink("socket:[4242]", fixtures.proc_path("1/fd/3")).unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    assert!(process(&data, 42).tcp_ports.is_empty());
+    assert_eq!(process(&data, 42).udp_ports, [5353]);
+    assert_eq!(process(&data, 1).tcp_ports, [8080]);
+}
+
+#[test]
+fn finds_sockets_once_they_are_bound() {
+    let fixtures = TempFixtures::new("bound-socket");
+    // A socket that is not bound yet is in no socket table
+    symlink("socket:[7000]", fixtures.proc_path("42/fd/5")).unwrap();
+    let mut reader = fixtures.reader();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).tcp_ports, [8080]);
+
+    let tcp = fs::read_to_string(fixtures.proc_path("net/tcp")).unwrap();
+    let listening = "   1: 00000000:1F91 00000000:0000 0A 00000000:00000000 00:00000000 \
+                     00000000  1000        0 7000 1 0000000000000000 100 0 0 10 0\n";
+    fs::write(fixtures.proc_path("net/tcp"), tcp + listening).unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let mut ports = process(&data, 42).tcp_ports.clone();
+    ports.sort_unstable();
+    assert_eq!(ports, [8080, 8081]);
+}
+
+#[test]
+fn rescans_the_fds_of_processes_whose_socket_closed() {
+    let fixtures = TempFixtures::new("closed-socket");
+    let mut reader = fixtures.reader();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).udp_ports, [5353]);
+
+    // The UDP socket is closed and its fd number taken by a file, as dup2 does
+    fs::remove_file(fixtures.proc_path("42/fd/4")).unwrap();
+    symlink("/dev/null", fixtures.proc_path("42/fd/4")).unwrap();
+    let udp = fs::read_to_string(fixtures.proc_path("net/udp")).unwrap();
+    fs::write(fixtures.proc_path("net/udp"), udp.lines().next().unwrap()).unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    assert!(process(&data, 42).udp_ports.is_empty());
+    assert_eq!(process(&data, 42).tcp_ports, [8080]);
+}
+
+#[test]
+fn rescans_the_fds_when_a_socket_takes_the_place_of_a_file() {
+    let fixtures = TempFixtures::new("replaced-file");
+    let mut reader = fixtures.reader();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).tcp_ports, [8080]);
+
+    // A file is closed and a socket opened in its fd number within the same tick
+    fs::remove_file(fixtures.proc_path("42/fd/0")).unwrap();
+    symlink("socket:[7000]", fixtures.proc_path("42/fd/0")).unwrap();
+    let tcp = fs::read_to_string(fixtures.proc_path("net/tcp")).unwrap();
+    let listening = "   1: 00000000:1F91 00000000:0000 0A 00000000:00000000 00:00000000 \
+                     00000000  1000        0 7000 1 0000000000000000 100 0 0 10 0\n";
+    fs::write(fixtures.proc_path("net/tcp"), tcp + listening).unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let mut ports = process(&data, 42).tcp_ports.clone();
+    ports.sort_unstable();
+    assert_eq!(ports, [8080, 8081]);
+}
+
+#[test]
+fn rereads_ids_every_tick_and_the_command_after_an_exec() {
+    let fixtures = TempFixtures::new("exec");
+    symlink("/usr/bin/worker", fixtures.proc_path("42/exe")).unwrap();
+    let mut reader = fixtures.reader();
+    reader.read_process_data().unwrap();
+
+    // setuid(0) without executing anything
+    let status = fs::read_to_string(fixtures.proc_path("42/status")).unwrap();
+    let status = status.replace("Uid:\t1000\t1000\t1000\t1000", "Uid:\t0\t0\t0\t0");
+    fs::write(fixtures.proc_path("42/status"), status).unwrap();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).uids.map(|uids| uids.real), Some(0));
+    assert_eq!(process(&data, 42).command, "worker --jobs 4");
+
+    // Executing another program under the same name
+    fs::write(fixtures.proc_path("42/cmdline"), "worker\0--jobs\08\0").unwrap();
+    fs::remove_file(fixtures.proc_path("42/exe")).unwrap();
+    symlink("/opt/worker/bin/worker", fixtures.proc_path("42/exe")).unwrap();
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).command, "worker --jobs 8");
+}
+
+#[test]
+fn sockets_first_seen_have_no_rate() {
+    let mut reader = Sockets::reader(vec![Some(HashMap::new()), dump(5000, 50)]);
+    reader.read_process_data().unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let worker = process(&data, 42);
+    assert_eq!(worker.total_net_received_bytes, 5000);
+    assert_eq!(worker.net_received_bytes_per_sec, 0.0);
+    assert_eq!(worker.net_sent_bytes_per_sec, 0.0);
+}
+
+#[test]
+fn keeps_the_last_dump_while_sock_diag_fails() {
+    let mut reader = Sockets::reader(vec![dump(1000, 10), None, dump(1500, 15)]);
+    reader.read_process_data().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let data = reader.read_process_data().unwrap();
+    assert_eq!(process(&data, 42).total_net_received_bytes, 1000);
+    assert_eq!(process(&data, 42).net_received_bytes_per_sec, 0.0);
+
+    // The 500 bytes are spread over both ticks since the last good dump
+    thread::sleep(Duration::from_millis(100));
+    let data = reader.read_process_data().unwrap();
+    let worker = process(&data, 42);
+    assert_eq!(worker.total_net_received_bytes, 1500);
+    assert!(worker.net_received_bytes_per_sec > 0.0);
+    assert!(worker.net_received_bytes_per_sec < 2600.0);
+}
+
+#[test]
+fn processes_first_seen_use_no_cpu() {
+    let fixtures = TempFixtures::new("cpu-time");
+    let mut reader = fixtures.reader();
+    let data = reader.read_process_data().unwrap();
+    assert!(data.processes.iter().all(|p| p.cpu_usage_percent == 0.0));
+
+    // The worker runs for another 50 ticks while the CPUs run for 100
+    let stat = fs::read_to_string(fixtures.proc_path("stat")).unwrap();
+    let stat = stat.replacen("cpu  10000 ", "cpu  10100 ", 1);
+    fs::write(fixtures.proc_path("stat"), stat).unwrap();
+    let worker_stat = fs::read_to_string(fixtures.proc_path("42/stat")).unwrap();
+    let mut fields: Vec<String> = worker_stat.split_whitespace().map(String::from).collect();
+    fields[13] = (fields[13].parse::<u64>().unwrap() + 50).to_string();
+    fs::write(fixtures.proc_path("42/stat"), fields.join(" ") + "\n").unwrap();
+    let data = reader.read_process_data().unwrap();
+
+    let worker = process(&data, 42).cpu_usage_percent;
+    assert!(worker > 0.0 && worker <= 100.0, "{}", worker);
+    assert_eq!(process(&data, 1).cpu_usage_percent, 0.0);
+}